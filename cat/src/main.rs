@@ -1,15 +1,34 @@
+use common::help::Tool;
+
 use std::{
     env,
     fs,
     path::Path
 };
 
+const CAT: Tool = Tool {
+    name: "cat",
+    version: env!("CARGO_PKG_VERSION"),
+    usage: "[OPTION]... [FILE]...",
+    description: "Concatenate FILE(s) to standard output.",
+    options: &[],
+    notes: ""
+};
+
 fn main() {
     let mut files: Vec<String> = Vec::new();
 
     let args: Vec<String> = env::args().collect();
     for arg in args.iter().skip(1) {
         match arg.as_str() {
+            "--help" => {
+                print!("{}", CAT.help());
+                return;
+            },
+            "--version" => {
+                print!("{}", CAT.version());
+                return;
+            },
             arg if arg.starts_with("--") => {},
             arg if arg.starts_with('-') => {},
             arg => files.push(arg.to_string())
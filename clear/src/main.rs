@@ -1,19 +1,47 @@
-use std::io::{self, Write};
+use common::help::Tool;
+
+use std::{
+    env,
+    io::{self, Write},
+    process
+};
 
 #[cfg(windows)]
 use std::process::Command;
 
+const CLEAR: Tool = Tool {
+    name: "clear",
+    version: env!("CARGO_PKG_VERSION"),
+    usage: "[OPTION]...",
+    description: "Clear the terminal screen.",
+    options: &[],
+    notes: ""
+};
+
 fn main() {
+    if let Some(arg) = env::args().nth(1) {
+        match arg.as_str() {
+            "--help" => {
+                print!("{}", CLEAR.help());
+                return;
+            },
+            "--version" => {
+                print!("{}", CLEAR.version());
+                return;
+            },
+            _ => {
+                println!("\x1b[0;91mError: Unknown argument '{}'.\x1b[0m", arg);
+                process::exit(1);
+            }
+        }
+    }
+
     #[cfg(windows)] {
-        match Command::new("cmd")
+        if let Ok(status) = Command::new("cmd")
                 .args(["/c", "cls"])
-                .status() {
-            Ok(status) => {
-                if status.success() {
-                    return;
-                }
-            },
-            Err(_) => {}
+                .status()
+            && status.success() {
+            return;
         }
     }
 
@@ -0,0 +1,175 @@
+//! GNU style backups of files which are about to be overwritten (`--backup[=CONTROL]`).
+
+use std::{
+    fs,
+    path::{Path, PathBuf}
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BackupControl {
+    None,
+    Numbered,
+    Existing,
+    Simple
+}
+
+impl BackupControl {
+    /// Parses a version control method as given to `--backup=` or `$VERSION_CONTROL`.
+    pub fn parse(version: &str) -> Option<Self> {
+        match version.to_lowercase().as_str() {
+            "t" | "numbered" => Some(BackupControl::Numbered),
+            "nil" | "existing" => Some(BackupControl::Existing),
+            "never" | "simple" => Some(BackupControl::Simple),
+            "none" | "off" => Some(BackupControl::None),
+            _ => None
+        }
+    }
+}
+
+/// The path `backup()` would move `path` to, without touching anything.
+pub fn backup_path(path: &Path, control: BackupControl, suffix: &str) -> Option<PathBuf> {
+    if path.is_dir() {
+        return None;
+    }
+
+    let original_file = path.file_name()?.to_string_lossy();
+    let parent = path.parent()?;
+
+    let backup_file = match control {
+        BackupControl::None => return None,
+        BackupControl::Simple => parent.join(format!("{}{}", original_file, suffix)),
+        BackupControl::Existing | BackupControl::Numbered => {
+            // a bare file name is in the current directory
+            let directory = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            let files = fs::read_dir(directory).ok()?;
+
+            let numbered_prefix = format!("{}.~", original_file);
+            let numbered_suffix = "~".to_string();
+
+            let simple_name = format!("{}{}", original_file, suffix);
+
+            let mut latest_backup: i64 = 0;
+            let mut found_simple: bool = false;
+            for entry in files.filter_map(|entry| entry.ok()) {
+                let entry_path = entry.path();
+                let filename = match entry_path.file_name() {
+                    Some(name) => name.to_string_lossy(),
+                    None => continue
+                };
+                match filename.strip_prefix(&numbered_prefix) {
+                    Some(filename) => {
+                        let filename = match filename.strip_suffix(&numbered_suffix) {
+                            Some(filename) if !filename.is_empty() => filename,
+                            _ => continue
+                        };
+                        let n: i64 = match filename.parse() {
+                            Ok(n) => n,
+                            Err(_) => continue
+                        };
+
+                        if n > latest_backup {
+                            latest_backup = n;
+                        }
+                    },
+                    None => {
+                        if filename == simple_name {
+                            found_simple = true;
+                        }
+                    }
+                }
+            }
+
+            if control == BackupControl::Numbered || latest_backup != 0 || found_simple {
+                parent.join(format!("{}.~{}~", original_file, latest_backup + 1))
+            } else {
+                parent.join(simple_name)
+            }
+        }
+    };
+
+    Some(backup_file)
+}
+
+/// Moves `path` out of the way, returning the file name of the backup.
+pub fn backup(path: &Path, control: BackupControl, suffix: &str) -> Option<String> {
+    let backup_file = backup_path(path, control, suffix)?;
+    let new_name = backup_file.file_name()?.to_string_lossy().to_string();
+
+    match fs::rename(path, &backup_file) {
+        Ok(_) => Some(new_name),
+        Err(_) => match fs::copy(path, &backup_file) {
+            Ok(_) => Some(new_name),
+            Err(_) => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory of its own for each test, holding `files`.
+    fn scratch(name: &str, files: &[&str]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("backup-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for file in files {
+            fs::write(directory.join(file), "").unwrap();
+        }
+        directory
+    }
+
+    fn name(path: Option<PathBuf>) -> Option<String> {
+        Some(path?.file_name()?.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn parse_control() {
+        assert!(BackupControl::parse("t") == Some(BackupControl::Numbered));
+        assert!(BackupControl::parse("NIL") == Some(BackupControl::Existing));
+        assert!(BackupControl::parse("simple") == Some(BackupControl::Simple));
+        assert!(BackupControl::parse("off") == Some(BackupControl::None));
+        assert!(BackupControl::parse("sometimes").is_none());
+    }
+
+    #[test]
+    fn simple_and_none() {
+        let directory = scratch("simple", &["f"]);
+        let file = directory.join("f");
+        assert_eq!(name(backup_path(&file, BackupControl::Simple, "~")), Some("f~".to_string()));
+        assert_eq!(name(backup_path(&file, BackupControl::Simple, ".bak")), Some("f.bak".to_string()));
+        assert_eq!(backup_path(&file, BackupControl::None, "~"), None);
+        assert_eq!(backup_path(&directory, BackupControl::Simple, "~"), None);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn numbered_counts_up_from_the_latest() {
+        let directory = scratch("numbered", &["f", "f.~1~", "f.~12~", "f.~x~", "f.~~", "g.~40~"]);
+        let file = directory.join("f");
+        assert_eq!(name(backup_path(&file, BackupControl::Numbered, "~")), Some("f.~13~".to_string()));
+        let fresh = directory.join("g2");
+        assert_eq!(name(backup_path(&fresh, BackupControl::Numbered, "~")), Some("g2.~1~".to_string()));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn existing_follows_what_is_there() {
+        let directory = scratch("existing", &["plain", "simple", "simple~", "numbered", "numbered.~2~"]);
+        let path = |file: &str| backup_path(&directory.join(file), BackupControl::Existing, "~");
+        assert_eq!(name(path("plain")), Some("plain~".to_string()));
+        assert_eq!(name(path("simple")), Some("simple.~1~".to_string()));
+        assert_eq!(name(path("numbered")), Some("numbered.~3~".to_string()));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn backup_moves_the_file() {
+        let directory = scratch("move", &["f"]);
+        let file = directory.join("f");
+        assert_eq!(backup(&file, BackupControl::Numbered, "~"), Some("f.~1~".to_string()));
+        assert!(!file.exists());
+        assert!(directory.join("f.~1~").exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,29 @@
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf}
+};
+
+/// `$XDG_CONFIG_HOME/cli-tools`, i.e. `~/.config/cli-tools` by default.
+pub fn dir() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(config_home) if Path::new(&config_home).is_absolute() => PathBuf::from(config_home),
+        _ => PathBuf::from(env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?).join(".config")
+    };
+    Some(config_home.join("cli-tools"))
+}
+
+/// Arguments from `<config dir>/<tool>.conf` which are applied before those on the command line.
+///
+/// The file holds one argument per line, blank lines and lines starting with `#` are ignored.
+pub fn default_args(tool: &str) -> Vec<String> {
+    let contents = match dir().and_then(|dir| fs::read_to_string(dir.join(format!("{}.conf", tool))).ok()) {
+        Some(contents) => contents,
+        None => return Vec::new()
+    };
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,254 @@
+//! Moving files between file systems, where `rename()` can't be used: the whole tree is copied (symlinks
+//! and special files included) and only then is the original removed.
+//!
+//! Like `mv` from coreutils, the copy keeps the permissions, access and modification times (to the
+//! nanosecond), owner and group (where permitted), extended attributes and ACLs of everything copied.
+
+use crate::xattr;
+
+use std::{
+    fs::{self, Metadata},
+    io,
+    path::{Path, PathBuf}
+};
+
+#[cfg(target_os = "linux")]
+use std::{
+    ffi::{c_long, CString},
+    os::unix::{ffi::OsStrExt, fs::MetadataExt}
+};
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn mknod(path: *const u8, mode: u32, dev: u64) -> i32;
+    fn utimensat(directory: i32, path: *const u8, times: *const Timespec, flags: i32) -> i32;
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct Timespec {
+    tv_sec: c_long,
+    tv_nsec: c_long
+}
+
+/// Something about a file which couldn't be carried over to its copy.
+pub struct Unpreserved {
+    /// the original file
+    pub path: PathBuf,
+    /// e.g. "ownership" or "extended attribute 'user.foo'"
+    pub what: String,
+    pub err: io::Error
+}
+
+#[cfg(target_os = "linux")]
+fn c_path(path: &Path) -> io::Result<CString> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
+/// Recreates a fifo, socket or device node. Like `cp -a`, a socket is only recreated as a name, nothing
+/// is listening on it any more.
+#[cfg(target_os = "linux")]
+fn copy_special(metadata: &Metadata, destination: &Path) -> io::Result<()> {
+    let path = c_path(destination)?;
+    // SAFETY: the path is a valid C string
+    if unsafe { mknod(path.as_ptr().cast(), metadata.mode(), metadata.rdev()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_special(_metadata: &Metadata, _destination: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "special files can't be copied on this platform"))
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, destination)
+}
+
+#[cfg(windows)]
+fn copy_symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+    let target = fs::read_link(source)?;
+    if fs::metadata(source).is_ok_and(|metadata| metadata.is_dir()) {
+        symlink_dir(target, destination)
+    } else {
+        symlink_file(target, destination)
+    }
+}
+
+/// Sets the access and modification times of `destination` (not following symlinks) to those in `metadata`.
+#[cfg(target_os = "linux")]
+fn copy_times(metadata: &Metadata, destination: &Path) -> io::Result<()> {
+    const AT_FDCWD: i32 = -100;
+    const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
+
+    let path = c_path(destination)?;
+    let times = [
+        Timespec { tv_sec: metadata.atime() as c_long, tv_nsec: metadata.atime_nsec() as c_long },
+        Timespec { tv_sec: metadata.mtime() as c_long, tv_nsec: metadata.mtime_nsec() as c_long }
+    ];
+    // SAFETY: the path is a valid C string and `times` holds the two timestamps utimensat expects
+    if unsafe { utimensat(AT_FDCWD, path.as_ptr().cast(), times.as_ptr(), AT_SYMLINK_NOFOLLOW) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_times(metadata: &Metadata, destination: &Path) -> io::Result<()> {
+    if metadata.is_symlink() {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "the times of a symlink can't be set on this platform"));
+    }
+    let times = fs::FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+    fs::File::open(destination)?.set_times(times)
+}
+
+/// Gives `destination` the owner and group in `metadata`, or at least the group if only that's permitted.
+#[cfg(unix)]
+fn copy_ownership(metadata: &Metadata, destination: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{MetadataExt, lchown};
+
+    let copied = fs::symlink_metadata(destination)?;
+    if copied.uid() == metadata.uid() && copied.gid() == metadata.gid() {
+        return Ok(());
+    }
+    lchown(destination, Some(metadata.uid()), Some(metadata.gid())).inspect_err(|_| {
+        let _ = lchown(destination, None, Some(metadata.gid()));
+    })
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_metadata: &Metadata, _destination: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Copies every extended attribute of `source` to `destination`. ACLs are stored as the
+/// `system.posix_acl_*` attributes, so they're copied along with the rest.
+fn copy_xattrs(source: &Path, destination: &Path, unpreserved: &mut Vec<Unpreserved>) {
+    let mut lost = |what: String, err: io::Error| unpreserved.push(Unpreserved {
+        path: source.to_path_buf(),
+        what,
+        err
+    });
+
+    let names = match xattr::list(source) {
+        Ok(names) => names,
+        // nothing to lose on a file system without them
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => return,
+        Err(err) => return lost("extended attributes".to_string(), err)
+    };
+    for name in names {
+        let what = match name.to_string_lossy() {
+            name if name.starts_with("system.posix_acl_") => "ACL".to_string(),
+            name => format!("extended attribute '{}'", name)
+        };
+        if let Err(err) = xattr::get(source, &name).and_then(|value| xattr::set(destination, &name, &value)) {
+            lost(what, err);
+        }
+    }
+}
+
+/// Carries everything but the contents of `source` over to `destination`.
+fn copy_attributes(source: &Path, metadata: &Metadata, destination: &Path, unpreserved: &mut Vec<Unpreserved>) {
+    let lost = |what: &str, err: io::Error| Unpreserved {
+        path: source.to_path_buf(),
+        what: what.to_string(),
+        err
+    };
+
+    if let Err(err) = copy_ownership(metadata, destination) {
+        unpreserved.push(lost("ownership", err));
+    }
+    // changing the owner clears the set-user-ID and set-group-ID bits, so only now;
+    // a symlink has no permissions of its own
+    if !metadata.is_symlink()
+        && let Err(err) = fs::set_permissions(destination, metadata.permissions()) {
+        unpreserved.push(lost("permissions", err));
+    }
+    copy_xattrs(source, destination, unpreserved);
+    if let Err(err) = copy_times(metadata, destination) {
+        unpreserved.push(lost("timestamps", err));
+    }
+}
+
+fn copy_entry(source: &Path, destination: &Path, unpreserved: &mut Vec<Unpreserved>) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(source)?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_dir() {
+        fs::create_dir(destination)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_entry(&entry.path(), &destination.join(entry.file_name()), unpreserved)?;
+        }
+    } else if file_type.is_symlink() {
+        copy_symlink(source, destination)?;
+    } else if file_type.is_file() {
+        fs::copy(source, destination)?;
+    } else {
+        copy_special(&metadata, destination)?;
+    }
+
+    // for a directory only once it's filled: a read-only one couldn't be, and filling it changes its times
+    copy_attributes(source, &metadata, destination, unpreserved);
+    Ok(())
+}
+
+/// Where `destination` is put together before it's renamed into place: a hidden name next to it, so on
+/// the same file system.
+fn staging_path(destination: &Path) -> PathBuf {
+    let name = destination.file_name().map_or("mv".into(), |name| name.to_string_lossy());
+    destination.with_file_name(format!(".{}.{}.partial", name, std::process::id()))
+}
+
+/// Copies `source`, and everything below it if it's a directory, to `destination`. The copy is made next
+/// to `destination` and then renamed over it, so `destination` is replaced in one step like `rename()`
+/// would, and is left untouched (with nothing partially copied lying around) if anything fails.
+///
+/// Returns what couldn't be preserved, which doesn't stop the copy.
+pub fn copy(source: &Path, destination: &Path) -> io::Result<Vec<Unpreserved>> {
+    let staging = staging_path(destination);
+    // it isn't ours to clean up
+    if fs::symlink_metadata(&staging).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists,
+            format!("'{}' is in the way", staging.to_string_lossy())));
+    }
+
+    let mut unpreserved: Vec<Unpreserved> = Vec::new();
+    let result = copy_entry(source, &staging, &mut unpreserved)
+        .and_then(|_| fs::rename(&staging, destination));
+    if let Err(err) = result {
+        let _ = remove(&staging);
+        return Err(err);
+    }
+    Ok(unpreserved)
+}
+
+/// Removes `path`, along with everything below it if it's a directory. Symlinks are never followed.
+pub fn remove(path: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return fs::remove_file(path);
+    }
+
+    // a read-only directory can't be emptied
+    let mut permissions = metadata.permissions();
+    if permissions.readonly() {
+        #[cfg(unix)] {
+            use std::os::unix::fs::PermissionsExt;
+            permissions.set_mode(permissions.mode() | 0o200);
+        }
+        #[cfg(not(unix))]
+        permissions.set_readonly(false);
+        let _ = fs::set_permissions(path, permissions);
+    }
+
+    for entry in fs::read_dir(path)? {
+        remove(&entry?.path())?;
+    }
+    fs::remove_dir(path)
+}
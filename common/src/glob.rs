@@ -0,0 +1,198 @@
+//! Shell style wildcards: `*`, `**`, `?` and `[...]` character classes.
+
+use std::path::Path;
+
+/// Matches a character class starting just after its `[`, returning whether `ch` is in it and the
+/// rest of the pattern after the closing `]`. `None` if the class isn't closed.
+fn match_class(pattern: &[char], ch: char) -> Option<(bool, &[char])> {
+    let (negated, mut rest) = match pattern.first() {
+        Some('!' | '^') => (true, &pattern[1..]),
+        _ => (false, pattern)
+    };
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let (&start, after) = rest.split_first()?;
+        // a `]` straight after the `[` is part of the class
+        if start == ']' && !first {
+            return Some((matched != negated, after));
+        }
+        first = false;
+
+        let (start, after) = match (start, after.split_first()) {
+            ('\\', Some((&escaped, after))) => (escaped, after),
+            _ => (start, after)
+        };
+        match after {
+            ['-', end, after @ ..] if *end != ']' => {
+                matched |= (start..=*end).contains(&ch);
+                rest = after;
+            },
+            _ => {
+                matched |= start == ch;
+                rest = after;
+            }
+        }
+    }
+}
+
+fn match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => {
+            // `**` may cross directory boundaries, `*` may not
+            let (crosses, rest) = match rest.split_first() {
+                Some(('*', rest)) => (true, rest),
+                _ => (false, rest)
+            };
+            for skip in 0..=text.len() {
+                if match_chars(rest, &text[skip..]) {
+                    return true;
+                }
+                if skip < text.len() && text[skip] == '/' && !crosses {
+                    return false;
+                }
+            }
+            false
+        },
+        Some(('?', rest)) => match text.split_first() {
+            Some((&ch, text)) if ch != '/' => match_chars(rest, text),
+            _ => false
+        },
+        Some(('[', rest)) => match text.split_first() {
+            Some((&ch, text)) => match match_class(rest, ch) {
+                Some((true, rest)) => match_chars(rest, text),
+                Some((false, _)) => false,
+                // an unclosed `[` is just a `[`
+                None => ch == '[' && match_chars(rest, text)
+            },
+            None => false
+        },
+        Some(('\\', [escaped, rest @ ..])) => match text.split_first() {
+            Some((ch, text)) if ch == escaped => match_chars(rest, text),
+            _ => false
+        },
+        Some((literal, rest)) => match text.split_first() {
+            Some((ch, text)) if ch == literal => match_chars(rest, text),
+            _ => false
+        }
+    }
+}
+
+/// Whether `text` matches the whole of `pattern`.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_chars(&pattern, &text)
+}
+
+/// Matches a pattern against a path like `.gitignore` does: a pattern without a `/` is matched against
+/// the file name alone, any other against the end of the path, starting at a component boundary.
+pub fn matches_path(pattern: &str, path: &Path) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    if !pattern.contains('/') {
+        return path.file_name()
+            .is_some_and(|name| matches(pattern, &name.to_string_lossy()));
+    }
+
+    let path = path.to_string_lossy();
+    let pattern = pattern.trim_start_matches("./");
+    if let Some(anchored) = pattern.strip_prefix('/') {
+        return matches(anchored, path.trim_start_matches('/'));
+    }
+    // try every suffix of the path which starts a component
+    let path = path.trim_start_matches("./");
+    std::iter::once(0)
+        .chain(path.match_indices('/').map(|(i, _)| i + 1))
+        .any(|start| matches(pattern, &path[start..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_stays_within_a_component() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("src/*", "src/main.rs"));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(!matches("src/*", "src/bin/main.rs"));
+    }
+
+    #[test]
+    fn double_star_crosses_components() {
+        assert!(matches("**.rs", "src/bin/main.rs"));
+        assert!(matches("src/**", "src/bin/main.rs"));
+        assert!(matches("src/**/main.rs", "src/bin/main.rs"));
+    }
+
+    #[test]
+    fn question_mark_is_one_character() {
+        assert!(matches("?.txt", "a.txt"));
+        assert!(!matches("?.txt", "ab.txt"));
+        assert!(!matches("?.txt", ".txt"));
+        assert!(!matches("a?b", "a/b"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches("[abc].txt", "b.txt"));
+        assert!(matches("[a-c].txt", "c.txt"));
+        assert!(!matches("[a-c].txt", "d.txt"));
+        assert!(matches("[!a-c].txt", "d.txt"));
+        assert!(!matches("[!a-c].txt", "a.txt"));
+        assert!(matches("[^a].txt", "b.txt"));
+    }
+
+    #[test]
+    fn leading_bracket_is_part_of_the_class() {
+        assert!(matches("[]]", "]"));
+        assert!(matches("[]a]", "a"));
+        assert!(!matches("[]a]", "b"));
+        assert!(matches("[!]]", "a"));
+        assert!(!matches("[!]]", "]"));
+    }
+
+    #[test]
+    fn unclosed_bracket_is_literal() {
+        assert!(matches("[abc", "[abc"));
+        assert!(matches("a[*", "a[bc"));
+        assert!(!matches("[abc", "a"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(matches("[\\]]", "]"));
+    }
+
+    #[test]
+    fn pattern_without_slash_matches_the_file_name() {
+        assert!(matches_path("*.log", Path::new("var/log/syslog.log")));
+        assert!(matches_path("target", Path::new("crate/target")));
+        assert!(!matches_path("target", Path::new("target/debug")));
+    }
+
+    #[test]
+    fn pattern_with_slash_matches_at_component_boundaries() {
+        assert!(matches_path("log/*.log", Path::new("var/log/syslog.log")));
+        assert!(matches_path("./log/*.log", Path::new("./var/log/syslog.log")));
+        assert!(!matches_path("og/*.log", Path::new("var/log/syslog.log")));
+    }
+
+    #[test]
+    fn leading_slash_anchors_the_pattern() {
+        assert!(matches_path("/src/*.rs", Path::new("src/main.rs")));
+        assert!(matches_path("/src/*.rs", Path::new("/src/main.rs")));
+        assert!(!matches_path("/src/*.rs", Path::new("crate/src/main.rs")));
+    }
+
+    #[test]
+    fn trailing_slash_is_ignored() {
+        assert!(matches_path("node_modules/", Path::new("web/node_modules")));
+        assert!(matches_path("web/node_modules/", Path::new("app/web/node_modules")));
+        assert!(!matches_path("node_modules/", Path::new("web/node_modules.txt")));
+    }
+}
@@ -0,0 +1,132 @@
+//! `--help` and `--version` output, generated from a table of each tool's options.
+
+const LINE_WIDTH: usize = 80;
+/// The column option descriptions start at.
+const DESCRIPTION_COLUMN: usize = 24;
+
+const REPOSITORY: &str = "https://github.com/Matt-DESTROYER/cli-tools";
+
+/// One option as listed by `--help`.
+pub struct Opt {
+    /// short names, e.g. `['r', 'R']` for `-r, -R`
+    pub short: &'static [char],
+    /// the long name without the leading `--`, or `""`
+    pub long: &'static str,
+    /// written straight after the name, e.g. `"[=WHEN]"`, `"=GLOB"` or `" N"`
+    pub value: &'static str,
+    pub description: &'static str
+}
+
+impl Opt {
+    pub const fn new(short: &'static [char], long: &'static str, description: &'static str) -> Self {
+        Opt {
+            short,
+            long,
+            value: "",
+            description
+        }
+    }
+
+    pub const fn value(self, value: &'static str) -> Self {
+        Opt { value, ..self }
+    }
+
+    fn names(&self) -> String {
+        let mut names: Vec<String> = self.short.iter().map(|short| format!("-{}", short)).collect();
+        if !self.long.is_empty() {
+            names.push(format!("--{}{}", self.long, self.value));
+        } else if let Some(last) = names.last_mut() {
+            last.push_str(self.value);
+        }
+        // long options line up whether or not there's a short one
+        let indent = if self.short.is_empty() { "      " } else { "  " };
+        format!("{}{}", indent, names.join(", "))
+    }
+}
+
+const HELP: Opt = Opt::new(&[], "help", "display this help and exit");
+const VERSION: Opt = Opt::new(&[], "version", "output version information and exit");
+
+/// Everything `--help` and `--version` print for a tool.
+pub struct Tool {
+    pub name: &'static str,
+    /// the crate version, i.e. `env!("CARGO_PKG_VERSION")`
+    pub version: &'static str,
+    /// the arguments in the usage line, e.g. `"[OPTION]... [FILE]..."`, one line per form of the command
+    pub usage: &'static str,
+    /// what the tool does, printed under the usage line
+    pub description: &'static str,
+    pub options: &'static [Opt],
+    /// anything else worth knowing, printed after the options
+    pub notes: &'static str
+}
+
+/// Word wraps `text` into a first line of at most `first` characters and then lines of at most `width`.
+fn wrap(text: &str, first: usize, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let limit = if lines.is_empty() { first } else { width };
+        if !line.is_empty() && line.len() + 1 + word.len() > limit {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+impl Tool {
+    fn option(option: &Opt) -> String {
+        let names = option.names();
+        // descriptions start in their own column, or straight after names too long for it
+        let start = DESCRIPTION_COLUMN.max(names.len() + 2);
+        let mut lines = wrap(
+            option.description,
+            LINE_WIDTH.saturating_sub(start),
+            LINE_WIDTH - DESCRIPTION_COLUMN - 2
+        ).into_iter();
+
+        let mut text = match lines.next() {
+            Some(first) => format!("{:width$}{}\n", names, first, width = start),
+            None => format!("{}\n", names)
+        };
+        for line in lines {
+            text.push_str(&format!("{:width$}{}\n", "", line, width = DESCRIPTION_COLUMN + 2));
+        }
+        text
+    }
+
+    pub fn help(&self) -> String {
+        let mut help = String::new();
+        for (i, usage) in self.usage.lines().enumerate() {
+            help.push_str(&format!("{} {} {}\n", if i == 0 { "Usage:" } else { "  or: " }, self.name, usage));
+        }
+        help.push_str(&format!("{}\n\n", self.description.trim_end()));
+        for option in self.options.iter().chain([&HELP, &VERSION]) {
+            help.push_str(&Tool::option(option));
+        }
+        if !self.notes.is_empty() {
+            help.push('\n');
+            help.push_str(self.notes.trim_end());
+            help.push('\n');
+        }
+        help.push_str(&format!("\nGitHub Repo: <{}>\n", REPOSITORY));
+        help
+    }
+
+    pub fn version(&self) -> String {
+        format!("{} (cli-tools) {}
+Copyright (c) 2025 Matthew James
+License: MIT <https://opensource.org/license/mit>
+This is free software: you are free to change and redistribute it.
+There is NO WARRANTY, to the extent permitted by law.
+
+GitHub Repo: <{}>\n", self.name, self.version, REPOSITORY)
+    }
+}
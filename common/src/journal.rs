@@ -0,0 +1,257 @@
+//! A log of the files `mv` and `rm --trash` moved, so that `undo` can put them back.
+//!
+//! The journal lives at `$XDG_STATE_HOME/cli-tools/journal` and holds one line per moved file:
+//! `<operation> <tool> <kind> <size> <modified> <from> <to>`, separated by tabs, with the paths
+//! percent-encoded. Every line written by one run of a tool shares the same operation id.
+
+use crate::{
+    percent,
+    time::unix_seconds
+};
+
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{self, Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// A file moved by `mv`.
+    Move,
+    /// An existing file moved out of the way by `--backup`.
+    Backup,
+    /// A file moved into the trash by `rm --trash`.
+    Trash
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Move => "move",
+            Kind::Backup => "backup",
+            Kind::Trash => "trash"
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "move" => Some(Kind::Move),
+            "backup" => Some(Kind::Backup),
+            "trash" => Some(Kind::Trash),
+            _ => None
+        }
+    }
+}
+
+/// The size and modification time of a file right after it was moved, to notice later changes.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Fingerprint {
+    pub size: u64,
+    /// nanoseconds since the UNIX epoch
+    pub modified: i128
+}
+
+impl Fingerprint {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::symlink_metadata(path).ok()?;
+        let modified = match metadata.modified().ok()?.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_nanos() as i128,
+            Err(err) => -(err.duration().as_nanos() as i128)
+        };
+        Some(Fingerprint {
+            size: metadata.len(),
+            modified
+        })
+    }
+}
+
+pub struct Entry {
+    pub kind: Kind,
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub fingerprint: Fingerprint
+}
+
+/// Everything one run of a tool moved, in the order it happened.
+pub struct Operation {
+    pub id: String,
+    /// when the operation started
+    pub time: SystemTime,
+    pub tool: String,
+    pub entries: Vec<Entry>
+}
+
+/// When the operation with the given id (`<seconds>-<pid>`) started, `None` if the id is malformed.
+fn time_of(id: &str) -> Option<SystemTime> {
+    let (seconds, pid) = id.split_once('-')?;
+    pid.parse::<u32>().ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(seconds.parse().ok()?))
+}
+
+/// `$XDG_STATE_HOME/cli-tools/journal`, i.e. `~/.local/state/cli-tools/journal` by default.
+pub fn path() -> Option<PathBuf> {
+    let state_home = match env::var_os("XDG_STATE_HOME") {
+        Some(state_home) if Path::new(&state_home).is_absolute() => PathBuf::from(state_home),
+        _ => PathBuf::from(env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?).join(".local/state")
+    };
+    Some(state_home.join("cli-tools").join("journal"))
+}
+
+/// The id shared by everything this process records.
+fn operation_id() -> &'static str {
+    static ID: OnceLock<String> = OnceLock::new();
+    ID.get_or_init(|| format!("{}-{}", unix_seconds(SystemTime::now()), std::process::id()))
+}
+
+/// Appends a move which has just happened to the journal. Each entry is written straight away, so
+/// whatever was moved before a crash can still be undone.
+pub fn record(tool: &str, kind: Kind, from: &Path, to: &Path) -> io::Result<()> {
+    let Some(journal) = path() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no home directory"));
+    };
+    let from = path::absolute(from)?;
+    let to = path::absolute(to)?;
+    let fingerprint = Fingerprint::of(&to)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the moved file is gone"))?;
+
+    if let Some(parent) = journal.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let line = format_line(operation_id(), tool, &Entry { kind, from, to, fingerprint });
+    // a single append keeps concurrent writers from interleaving within a line
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal)?
+        .write_all(line.as_bytes())
+}
+
+fn format_line(id: &str, tool: &str, entry: &Entry) -> String {
+    format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        id,
+        tool,
+        entry.kind.as_str(),
+        entry.fingerprint.size,
+        entry.fingerprint.modified,
+        percent::encode(&entry.from.to_string_lossy()),
+        percent::encode(&entry.to.to_string_lossy()))
+}
+
+/// Splits a journal line into its operation id, tool and entry. `None` if the line is corrupt.
+fn parse_line(line: &str) -> Option<(String, SystemTime, String, Entry)> {
+    let mut fields = line.split('\t');
+    let id = fields.next()?.to_string();
+    let time = time_of(&id)?;
+    let tool = fields.next()?.to_string();
+    let kind = Kind::parse(fields.next()?)?;
+    let fingerprint = Fingerprint {
+        size: fields.next()?.parse().ok()?,
+        modified: fields.next()?.parse().ok()?
+    };
+    let from = PathBuf::from(percent::decode(fields.next()?));
+    let to = PathBuf::from(percent::decode(fields.next()?));
+    if fields.next().is_some() {
+        return None;
+    }
+    Some((id, time, tool, Entry { kind, from, to, fingerprint }))
+}
+
+/// Every operation in the journal, oldest first. Malformed lines are ignored.
+pub fn read() -> Vec<Operation> {
+    let contents = match path().and_then(|journal| fs::read_to_string(journal).ok()) {
+        Some(contents) => contents,
+        None => return Vec::new()
+    };
+
+    let mut operations: Vec<Operation> = Vec::new();
+    for (id, time, tool, entry) in contents.lines().filter_map(parse_line) {
+        match operations.iter_mut().rev().find(|operation| operation.id == id) {
+            Some(operation) => operation.entries.push(entry),
+            None => operations.push(Operation {
+                id,
+                time,
+                tool,
+                entries: vec![entry]
+            })
+        }
+    }
+    operations
+}
+
+/// Drops the last `count` entries of an operation from the journal once they have been undone.
+pub fn forget(id: &str, count: usize) -> io::Result<()> {
+    let Some(journal) = path() else {
+        return Ok(());
+    };
+    let contents = fs::read_to_string(&journal)?;
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut to_forget: Vec<usize> = (0..lines.len())
+        .filter(|&i| lines[i].split('\t').next() == Some(id))
+        .collect();
+    to_forget.drain(..to_forget.len().saturating_sub(count));
+
+    let remaining: String = lines.iter()
+        .enumerate()
+        .filter(|(i, _)| !to_forget.contains(i))
+        .map(|(_, line)| format!("{}\n", line))
+        .collect();
+
+    // replace the journal in one go so a crash can't leave it half written
+    let temporary = journal.with_extension("tmp");
+    fs::write(&temporary, remaining)?;
+    fs::rename(temporary, journal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: Kind, from: &str, to: &str) -> Entry {
+        Entry {
+            kind,
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+            fingerprint: Fingerprint { size: 42, modified: -1_500_000_000_123 }
+        }
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        for kind in [Kind::Move, Kind::Backup, Kind::Trash] {
+            let original = entry(kind, "/home/me/a file\twith tab%", "/home/me/.Trash/files/a:b");
+            let line = format_line("1700000000-42", "mv", &original);
+            let (id, time, tool, parsed) = parse_line(line.trim_end()).unwrap();
+            assert_eq!(id, "1700000000-42");
+            assert_eq!(time, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+            assert_eq!(tool, "mv");
+            assert!(parsed.kind == original.kind);
+            assert_eq!(parsed.from, original.from);
+            assert_eq!(parsed.to, original.to);
+            assert!(parsed.fingerprint == original.fingerprint);
+        }
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        let valid = "1700000000-42\tmv\tmove\t3\t5\t/a\t/b";
+        assert!(parse_line(valid).is_some());
+        for line in [
+            "",
+            "1700000000-42\tmv\tmove\t3\t5\t/a",
+            "1700000000-42\tmv\tmove\t3\t5\t/a\t/b\textra",
+            "1700000000-42\tmv\tcopy\t3\t5\t/a\t/b",
+            "1700000000-42\tmv\tmove\t-3\t5\t/a\t/b",
+            "1700000000-42\tmv\tmove\t3\tsoon\t/a\t/b",
+            "1700000000\tmv\tmove\t3\t5\t/a\t/b",
+            "yesterday-42\tmv\tmove\t3\t5\t/a\t/b",
+            "18446744073709551615-1\tmv\tmove\t3\t5\t/a\t/b",
+            "18446744073709551616-1\tmv\tmove\t3\t5\t/a\t/b"
+        ] {
+            assert!(parse_line(line).is_none(), "{:?}", line);
+        }
+    }
+}
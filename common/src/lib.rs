@@ -0,0 +1,15 @@
+//! Code shared between the cli-tools binaries.
+
+pub mod backup;
+pub mod config;
+pub mod copy;
+pub mod glob;
+pub mod help;
+pub mod journal;
+pub mod percent;
+pub mod pool;
+pub mod protect;
+pub mod size;
+pub mod time;
+pub mod trash;
+pub mod xattr;
@@ -0,0 +1,32 @@
+//! Percent-encoding of paths, as used in trashinfo files, the journal and `file://` URLs.
+
+/// Percent-encodes everything except unreserved characters, path separators and drive colons.
+pub fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9'
+                | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte))
+        }
+    }
+    encoded
+}
+
+pub fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = text.get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,146 @@
+//! A pool of threads working through a shared stack of jobs, where a job may queue more jobs (e.g. the
+//! subdirectories found while scanning a directory). The pool is done once nothing is queued or running.
+
+use std::{
+    sync::{Condvar, Mutex, MutexGuard},
+    thread
+};
+
+struct State<T> {
+    /// last in, first out, so a tree is walked depth first
+    jobs: Vec<T>,
+    /// jobs which have been queued but haven't finished yet
+    unfinished: usize
+}
+
+pub struct Pool<T> {
+    state: Mutex<State<T>>,
+    /// signalled whenever a job is queued and once the last one has finished
+    changed: Condvar
+}
+
+/// Marks a job as finished when dropped, even if it panicked, so the other workers don't wait forever.
+struct Finished<'a, T>(&'a Pool<T>);
+
+impl<T> Drop for Finished<'_, T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.unfinished -= 1;
+        if state.unfinished == 0 {
+            self.0.changed.notify_all();
+        }
+    }
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Pool::new()
+    }
+}
+
+impl<T> Pool<T> {
+    pub fn new() -> Self {
+        Pool {
+            state: Mutex::new(State {
+                jobs: Vec::new(),
+                unfinished: 0
+            }),
+            changed: Condvar::new()
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn push(&self, job: T) {
+        self.extend([job]);
+    }
+
+    /// Queues several jobs at once. The last one is the first to be taken.
+    pub fn extend(&self, jobs: impl IntoIterator<Item = T>) {
+        let mut state = self.lock();
+        let queued = state.jobs.len();
+        state.jobs.extend(jobs);
+        let added = state.jobs.len() - queued;
+        state.unfinished += added;
+        drop(state);
+        match added {
+            0 => {},
+            1 => self.changed.notify_one(),
+            _ => self.changed.notify_all()
+        }
+    }
+
+    /// The next job, or `None` once every job has finished.
+    fn next(&self) -> Option<T> {
+        let mut state = self.lock();
+        loop {
+            if let Some(job) = state.jobs.pop() {
+                return Some(job);
+            }
+            if state.unfinished == 0 {
+                return None;
+            }
+            // checked and waited on under the same lock, so no push or finish can slip in between
+            state = self.changed.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Works through jobs on the calling thread until every job, including those queued by other
+    /// jobs, has finished. Any number of threads may do so at once.
+    pub fn work(&self, run: impl Fn(T)) {
+        while let Some(job) = self.next() {
+            let _finished = Finished(self);
+            run(job);
+        }
+    }
+}
+
+impl<T: Send> Pool<T> {
+    /// Runs everything queued on `workers` threads and returns once all of it has finished.
+    pub fn run(&self, workers: usize, run: impl Fn(T) + Sync) {
+        thread::scope(|scope| {
+            for _ in 0..workers.max(1) {
+                scope.spawn(|| self.work(&run));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn jobs_queued_by_jobs_are_run() {
+        let pool = Pool::new();
+        let count = AtomicUsize::new(0);
+        pool.push(0u32);
+        // a binary tree 10 levels deep
+        pool.run(4, |depth| {
+            count.fetch_add(1, Ordering::Relaxed);
+            if depth < 9 {
+                pool.extend([depth + 1, depth + 1]);
+            }
+        });
+        assert_eq!(count.into_inner(), (1 << 10) - 1);
+    }
+
+    #[test]
+    fn nothing_to_do() {
+        let pool: Pool<u32> = Pool::new();
+        pool.run(4, |_| unreachable!());
+    }
+
+    #[test]
+    fn last_in_first_out() {
+        let pool = Pool::new();
+        pool.extend([1, 2, 3]);
+        let order = Mutex::new(Vec::new());
+        pool.run(1, |job| order.lock().unwrap().push(job));
+        assert_eq!(order.into_inner().unwrap(), [3, 2, 1]);
+    }
+}
@@ -0,0 +1,177 @@
+//! Paths which `rm` won't remove and `mv` won't overwrite unless given `--force-protected`.
+//!
+//! Patterns come from `<config dir>/protect`, which applies everywhere, and from `.rmprotect` files,
+//! which apply to the directory they're in and everything below it. Both hold one glob per line;
+//! blank lines and lines starting with `#` are ignored. Anything inside a protected directory is
+//! protected as well, as is every `.rmprotect` with at least one pattern in it.
+
+use crate::{config, glob};
+
+use std::{
+    collections::HashMap,
+    env,
+    fs,
+    path::{self, Component, Path, PathBuf},
+    sync::{Arc, Mutex}
+};
+
+struct Rule {
+    pattern: String,
+    /// the directory of the `.rmprotect` file the rule came from, `None` for the user's rules
+    base: Option<PathBuf>
+}
+
+pub struct Protection {
+    user: Vec<Rule>,
+    user_file: Option<PathBuf>,
+    /// the rules of every `.rmprotect` looked at so far, by directory
+    projects: Mutex<HashMap<PathBuf, Arc<Vec<Rule>>>>
+}
+
+fn parse(contents: &str, base: Option<&Path>) -> Vec<Rule> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let pattern = match (line.strip_prefix("~/"), &home) {
+                (Some(rest), Some(home)) if base.is_none() => home.join(rest).to_string_lossy().to_string(),
+                _ => line.to_string()
+            };
+            Rule {
+                pattern,
+                base: base.map(Path::to_path_buf)
+            }
+        })
+        .collect()
+}
+
+/// Makes `path` absolute and resolves `.` and `..` without touching the file system, so that
+/// `../secret` is matched the same way as the path it stands for.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path::absolute(path).ok()?.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component)
+        }
+    }
+    Some(normalized)
+}
+
+impl Rule {
+    fn matches(&self, path: &Path) -> bool {
+        let Some(base) = &self.base else {
+            return glob::matches_path(&self.pattern, path);
+        };
+        let Ok(relative) = path.strip_prefix(base) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        // a pattern with a '/' is anchored to the directory of the `.rmprotect`
+        let pattern = self.pattern.trim_end_matches('/');
+        match pattern.strip_prefix('/') {
+            Some(anchored) => glob::matches(anchored, &relative.to_string_lossy()),
+            None if pattern.contains('/') => glob::matches(pattern, &relative.to_string_lossy()),
+            None => glob::matches_path(pattern, relative)
+        }
+    }
+}
+
+impl Protection {
+    pub fn load() -> Self {
+        let user_file = config::dir().map(|dir| dir.join("protect"));
+        let user = user_file.as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|contents| parse(&contents, None))
+            .unwrap_or_default();
+        Protection {
+            user,
+            user_file,
+            projects: Mutex::new(HashMap::new())
+        }
+    }
+
+    fn project_rules(&self, directory: &Path) -> Arc<Vec<Rule>> {
+        if let Some(rules) = self.projects.lock().unwrap().get(directory) {
+            return rules.clone();
+        }
+        let rules = Arc::new(fs::read_to_string(directory.join(".rmprotect"))
+            .map(|contents| parse(&contents, Some(directory)))
+            .unwrap_or_default());
+        self.projects.lock().unwrap().insert(directory.to_path_buf(), rules.clone());
+        rules
+    }
+
+    /// Explains why `path` is protected, e.g. `'.git' in /home/me/project/.rmprotect`, or `None` if it isn't.
+    /// A `.rmprotect` is protected by "itself".
+    pub fn reason(&self, path: &Path) -> Option<String> {
+        let path = normalize(path)?;
+
+        // otherwise `rm -r` would take the protection away along with everything else
+        if path.file_name().is_some_and(|name| name == ".rmprotect")
+            && let Some(directory) = path.parent()
+            && !self.project_rules(directory).is_empty() {
+            return Some("itself".to_string());
+        }
+
+        for candidate in path.ancestors() {
+            if let Some(rule) = self.user.iter().find(|rule| rule.matches(candidate)) {
+                let source = self.user_file.as_ref().map_or(String::new(), |file| file.to_string_lossy().to_string());
+                return Some(format!("'{}' in {}", rule.pattern, source));
+            }
+        }
+
+        for directory in path.ancestors().skip(1) {
+            let rules = self.project_rules(directory);
+            for candidate in path.ancestors().take_while(|candidate| *candidate != directory) {
+                if let Some(rule) = rules.iter().find(|rule| rule.matches(candidate)) {
+                    return Some(format!("'{}' in {}", rule.pattern, directory.join(".rmprotect").to_string_lossy()));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protection(rules: &str) -> Protection {
+        Protection {
+            user: parse(rules, None),
+            user_file: Some(PathBuf::from("/config/protect")),
+            projects: Mutex::new(HashMap::new())
+        }
+    }
+
+    #[test]
+    fn normalize_resolves_dots() {
+        assert_eq!(normalize(Path::new("/home/me/x/../secret")), Some(PathBuf::from("/home/me/secret")));
+        assert_eq!(normalize(Path::new("/home/./me/")), Some(PathBuf::from("/home/me")));
+        assert_eq!(normalize(Path::new("/../..")), Some(PathBuf::from("/")));
+    }
+
+    #[test]
+    fn user_rules_match_through_parent_components() {
+        let protection = protection("/nonexistent/home/secret\n");
+        assert!(protection.reason(Path::new("/nonexistent/home/secret")).is_some());
+        assert!(protection.reason(Path::new("/nonexistent/home/x/../secret")).is_some());
+        assert!(protection.reason(Path::new("/nonexistent/home/./secret/file")).is_some());
+        assert!(protection.reason(Path::new("/nonexistent/home/secret/../other")).is_none());
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_ignored() {
+        let protection = protection("# *\n\n   \n*.key\n");
+        assert!(protection.reason(Path::new("/nonexistent/id.key")).is_some());
+        assert!(protection.reason(Path::new("/nonexistent/id.pub")).is_none());
+    }
+}
@@ -0,0 +1,93 @@
+//! Human readable file sizes.
+
+/// Formats a number of bytes like `ls -h`, e.g. `999`, `1.5K`, `12M`.
+pub fn format(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    size /= 1024.0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    // one decimal place for small numbers, always rounding up so nothing looks smaller than it is
+    if size < 10.0 {
+        let tenths = (size * 10.0).ceil();
+        if tenths < 100.0 {
+            return format!("{:.1}{}", tenths / 10.0, UNITS[unit]);
+        }
+    }
+    format!("{}{}", size.ceil() as u64, UNITS[unit])
+}
+
+/// Parses a size like `4096`, `10K`, `1.5M` or `2GiB` (powers of 1024) into bytes.
+pub fn parse(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text.find(|ch: char| !ch.is_ascii_digit() && ch != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let unit = unit.trim_end_matches("iB").trim_end_matches('B');
+    let exponent = match unit.to_uppercase().as_str() {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        "E" => 6,
+        _ => return None
+    };
+    Some((number * 1024f64.powi(exponent)) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bytes() {
+        assert_eq!(parse("0"), Some(0));
+        assert_eq!(parse("4096"), Some(4096));
+        assert_eq!(parse(" 512 "), Some(512));
+        assert_eq!(parse("100B"), Some(100));
+    }
+
+    #[test]
+    fn parse_units() {
+        assert_eq!(parse("10K"), Some(10 * 1024));
+        assert_eq!(parse("10k"), Some(10 * 1024));
+        assert_eq!(parse("1.5M"), Some(1536 * 1024));
+        assert_eq!(parse("2GiB"), Some(2 << 30));
+        assert_eq!(parse("1GB"), Some(1 << 30));
+        assert_eq!(parse("1E"), Some(1 << 60));
+    }
+
+    #[test]
+    fn parse_rejects_nonsense() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("K"), None);
+        assert_eq!(parse("10X"), None);
+        assert_eq!(parse("-1"), None);
+        assert_eq!(parse("1.2.3"), None);
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format(0), "0");
+        assert_eq!(format(999), "999");
+        assert_eq!(format(1023), "1023");
+        assert_eq!(format(1024), "1.0K");
+        assert_eq!(format(1025), "1.1K");
+        assert_eq!(format(1536), "1.5K");
+        assert_eq!(format(10 * 1024 - 1), "10K");
+        assert_eq!(format(12 * 1024 * 1024), "12M");
+        assert_eq!(format(u64::MAX), "16E");
+    }
+}
@@ -0,0 +1,251 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A calendar date and time of day, in local time unless stated otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32
+}
+
+/// The local time zone's offset from UTC (in seconds) at the given time.
+#[cfg(unix)]
+pub fn utc_offset(seconds: i64) -> i64 {
+    use std::ffi::c_long;
+
+    #[repr(C)]
+    struct Tm {
+        tm_sec: i32,
+        tm_min: i32,
+        tm_hour: i32,
+        tm_mday: i32,
+        tm_mon: i32,
+        tm_year: i32,
+        tm_wday: i32,
+        tm_yday: i32,
+        tm_isdst: i32,
+        tm_gmtoff: c_long,
+        tm_zone: *const u8
+    }
+
+    unsafe extern "C" {
+        fn localtime_r(time: *const c_long, result: *mut Tm) -> *mut Tm;
+    }
+
+    // a 32-bit `time_t` only reaches 2038
+    let Some(seconds) = c_long::try_from(seconds).ok() else {
+        return 0;
+    };
+    let mut tm = std::mem::MaybeUninit::<Tm>::zeroed();
+    // SAFETY: both pointers are valid for the duration of the call
+    let offset = unsafe {
+        if localtime_r(&seconds, tm.as_mut_ptr()).is_null() {
+            return 0;
+        }
+        tm.assume_init().tm_gmtoff
+    };
+    // a no-op on 64-bit targets, where `c_long` already is `i64`
+    #[allow(clippy::unnecessary_cast)]
+    let offset = offset as i64;
+    offset
+}
+
+#[cfg(not(unix))]
+pub fn utc_offset(_seconds: i64) -> i64 {
+    0
+}
+
+/// Converts days since the UNIX epoch to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as u32, day as u32)
+}
+
+/// Converts a civil date to days since the UNIX epoch, `None` on overflow.
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    let year = if month <= 2 { year.checked_sub(1)? } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = (month as i64 + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era.checked_mul(146_097)?.checked_add(day_of_era - 719_468)
+}
+
+/// Parses a duration like `90s`, `30m`, `12h`, `7d` or `2w`. A number without a unit is in days.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let split = text.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None
+    };
+    Some(Duration::from_secs(number.checked_mul(seconds)?))
+}
+
+pub fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64)
+    }
+}
+
+/// `None` if the time can't be represented.
+fn from_unix_seconds(seconds: i64) -> Option<SystemTime> {
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+impl DateTime {
+    pub fn local(time: SystemTime) -> Self {
+        let seconds = unix_seconds(time);
+        let local = seconds + utc_offset(seconds);
+        let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+        let time_of_day = local.rem_euclid(86_400) as u32;
+        DateTime {
+            year,
+            month,
+            day,
+            hour: time_of_day / 3600,
+            minute: time_of_day % 3600 / 60,
+            second: time_of_day % 60
+        }
+    }
+
+    /// Converts a local date and time back to a point in time, `None` if it's too far off to represent.
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        let local = days_from_civil(self.year, self.month, self.day)?
+            .checked_mul(86_400)?
+            .checked_add((self.hour * 3600 + self.minute * 60 + self.second) as i64)?;
+        // the offset at the local time is close enough to the offset at the actual time
+        from_unix_seconds(local.checked_sub(utc_offset(local))?)
+    }
+
+    /// Formats as `YYYY-MM-DDThh:mm:ss` (RFC 3339 without a time zone).
+    pub fn to_rfc3339(self) -> String {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day,
+            self.hour, self.minute, self.second)
+    }
+
+    /// Parses `YYYY-MM-DDThh:mm:ss` as written by `to_rfc3339()`.
+    pub fn parse_rfc3339(text: &str) -> Option<Self> {
+        let (date, time) = text.trim().split_once('T')?;
+        let mut date = date.splitn(3, '-');
+        let mut time = time.splitn(3, ':');
+        let date_time = DateTime {
+            year: date.next()?.parse().ok()?,
+            month: date.next()?.parse().ok()?,
+            day: date.next()?.parse().ok()?,
+            hour: time.next()?.parse().ok()?,
+            minute: time.next()?.parse().ok()?,
+            // ignore fractions of a second and time zones
+            second: time.next()?.get(..2)?.parse().ok()?
+        };
+        // RFC 3339 years have four digits, which also keeps the arithmetic on them from overflowing
+        if !(0..=9999).contains(&date_time.year)
+            || !(1..=12).contains(&date_time.month)
+            || !(1..=31).contains(&date_time.day) {
+            return None;
+        }
+        Some(date_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("12h"), Some(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(parse_duration("7d"), Some(Duration::from_secs(7 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("2w"), Some(Duration::from_secs(14 * 24 * 60 * 60)));
+    }
+
+    #[test]
+    fn parse_duration_defaults_to_days() {
+        assert_eq!(parse_duration("3"), Some(Duration::from_secs(3 * 24 * 60 * 60)));
+        assert_eq!(parse_duration(" 0 "), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn parse_duration_rejects_nonsense() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("5y"), None);
+        assert_eq!(parse_duration("-5d"), None);
+        assert_eq!(parse_duration("1.5h"), None);
+    }
+
+    #[test]
+    fn civil_dates_round_trip() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        for days in [-800_000, -1, 0, 59, 11_016, 20_000, 800_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), Some(days));
+        }
+    }
+
+    #[test]
+    fn rfc3339_round_trip() {
+        let date_time = DateTime { year: 2024, month: 2, day: 29, hour: 23, minute: 5, second: 9 };
+        assert_eq!(date_time.to_rfc3339(), "2024-02-29T23:05:09");
+        assert_eq!(DateTime::parse_rfc3339("2024-02-29T23:05:09"), Some(date_time));
+        assert_eq!(DateTime::parse_rfc3339("2024-02-29T23:05:09.5+01:00"), Some(date_time));
+    }
+
+    #[test]
+    fn rfc3339_rejects_nonsense() {
+        assert_eq!(DateTime::parse_rfc3339("2024-13-01T00:00:00"), None);
+        assert_eq!(DateTime::parse_rfc3339("2024-02-00T00:00:00"), None);
+        assert_eq!(DateTime::parse_rfc3339("2024-02-29"), None);
+        assert_eq!(DateTime::parse_rfc3339("2024-02-29T23:05"), None);
+        assert_eq!(DateTime::parse_rfc3339("99999999999999-01-01T00:00:00"), None);
+        assert_eq!(DateTime::parse_rfc3339("-1-01-01T00:00:00"), None);
+    }
+
+    #[test]
+    fn local_time_round_trip() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(DateTime::local(time).to_system_time(), Some(time));
+    }
+
+    #[test]
+    fn unrepresentable_times() {
+        for year in [i64::MAX, i64::MIN, i64::MAX / 400] {
+            let far_off = DateTime { year, month: 1, day: 1, hour: 0, minute: 0, second: 0 };
+            assert_eq!(far_off.to_system_time(), None);
+        }
+    }
+
+    #[test]
+    fn parse_duration_overflow() {
+        assert_eq!(parse_duration("18446744073709551615w"), None);
+        assert_eq!(parse_duration("18446744073709551616"), None);
+    }
+}
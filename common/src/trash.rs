@@ -0,0 +1,399 @@
+//! The FreeDesktop.org trash specification:
+//! <https://specifications.freedesktop.org/trash-spec/latest/>
+
+use std::{
+    collections::HashSet,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{self, Path, PathBuf},
+    time::SystemTime
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::{
+    DirBuilderExt,
+    MetadataExt,
+    PermissionsExt
+};
+
+use crate::{
+    percent,
+    time::DateTime
+};
+
+/// A trash directory, containing `files/` (the trashed files) and `info/` (their `.trashinfo`).
+#[derive(Clone, Debug)]
+pub struct TrashDir {
+    pub root: PathBuf,
+    /// the top directory of the mount this trash belongs to, `None` for the home trash
+    pub top: Option<PathBuf>
+}
+
+/// A file (or directory) in one of the trash directories.
+#[derive(Clone, Debug)]
+pub struct TrashedItem {
+    pub trash_dir: TrashDir,
+    /// the name under `files/`, which can differ from the original file name
+    pub name: String,
+    pub info: TrashInfo
+}
+
+/// The contents of a `.trashinfo` file.
+#[derive(Clone, Debug)]
+pub struct TrashInfo {
+    /// the absolute path the file was trashed from
+    pub path: PathBuf,
+    pub deletion_date: Option<DateTime>
+}
+
+#[cfg(unix)]
+pub fn uid() -> u32 {
+    unsafe extern "C" {
+        fn getuid() -> u32;
+    }
+    // SAFETY: getuid can't fail
+    unsafe { getuid() }
+}
+
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder.create(path)
+}
+
+impl TrashDir {
+    pub fn files(&self) -> PathBuf {
+        self.root.join("files")
+    }
+
+    pub fn info(&self) -> PathBuf {
+        self.root.join("info")
+    }
+
+    /// `$XDG_DATA_HOME/Trash`, i.e. `~/.local/share/Trash` by default.
+    pub fn home() -> Option<Self> {
+        let data_home = match env::var_os("XDG_DATA_HOME") {
+            Some(data_home) if Path::new(&data_home).is_absolute() => PathBuf::from(data_home),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".local/share")
+        };
+        Some(TrashDir {
+            root: data_home.join("Trash"),
+            top: None
+        })
+    }
+
+    /// The trash directories on the mount with the top directory `top`, whether or not they exist:
+    /// `$top/.Trash/$uid` (only valid when `.Trash` is a sticky directory) and `$top/.Trash-$uid`.
+    #[cfg(unix)]
+    pub fn for_top(top: &Path) -> Vec<Self> {
+        let uid = uid();
+        let mut trash_dirs = Vec::new();
+
+        let shared = top.join(".Trash");
+        if let Ok(metadata) = fs::symlink_metadata(&shared)
+            && metadata.is_dir()
+            && metadata.permissions().mode() & 0o1000 != 0 {
+            trash_dirs.push(TrashDir {
+                root: shared.join(uid.to_string()),
+                top: Some(top.to_path_buf())
+            });
+        }
+        trash_dirs.push(TrashDir {
+            root: top.join(format!(".Trash-{}", uid)),
+            top: Some(top.to_path_buf())
+        });
+
+        trash_dirs
+    }
+
+    /// Every trash directory which currently exists: the home trash and those of each mount.
+    pub fn all() -> Vec<Self> {
+        let mut trash_dirs: Vec<TrashDir> = TrashDir::home().into_iter().collect();
+        #[cfg(unix)] {
+            for mount_point in mount_points() {
+                trash_dirs.extend(TrashDir::for_top(&mount_point));
+            }
+        }
+        // the same mount can be listed more than once (e.g. bind mounts)
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        trash_dirs.retain(|trash_dir| trash_dir.info().is_dir() && seen.insert(trash_dir.root.clone()));
+        trash_dirs
+    }
+
+    /// The items in this trash directory.
+    /// `.trashinfo` files without a matching file are ignored, as the spec requires.
+    pub fn items(&self) -> Vec<TrashedItem> {
+        let entries = match fs::read_dir(self.info()) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new()
+        };
+        entries.filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let name = file_name.strip_suffix(".trashinfo")?.to_string();
+                fs::symlink_metadata(self.files().join(&name)).ok()?;
+                let info = self.read_info(&name).ok()?;
+                Some(TrashedItem { trash_dir: self.clone(), name, info })
+            })
+            .collect()
+    }
+
+    fn create(&self) -> io::Result<()> {
+        create_private_dir(&self.files())?;
+        create_private_dir(&self.info())
+    }
+
+    /// Reads the `.trashinfo` file for the trashed file `name`.
+    pub fn read_info(&self, name: &str) -> io::Result<TrashInfo> {
+        let contents = fs::read_to_string(self.info().join(format!("{}.trashinfo", name)))?;
+        TrashInfo::parse(&contents, self.top.as_deref())
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid .trashinfo file"))
+    }
+}
+
+impl TrashedItem {
+    /// Where the trashed file itself is.
+    pub fn file(&self) -> PathBuf {
+        self.trash_dir.files().join(&self.name)
+    }
+
+    pub fn info_file(&self) -> PathBuf {
+        self.trash_dir.info().join(format!("{}.trashinfo", self.name))
+    }
+
+    /// Permanently deletes the item (and its `.trashinfo` file).
+    pub fn purge(&self) -> io::Result<()> {
+        let file = self.file();
+        if fs::symlink_metadata(&file)?.is_dir() {
+            fs::remove_dir_all(&file)?;
+        } else {
+            fs::remove_file(&file)?;
+        }
+        fs::remove_file(self.info_file())
+    }
+}
+
+impl TrashInfo {
+    pub fn parse(contents: &str, top: Option<&Path>) -> Option<Self> {
+        let mut lines = contents.lines().map(str::trim);
+        if lines.next()? != "[Trash Info]" {
+            return None;
+        }
+
+        let mut path: Option<PathBuf> = None;
+        let mut deletion_date: Option<DateTime> = None;
+        for line in lines {
+            match line.split_once('=') {
+                Some(("Path", value)) => {
+                    let value = PathBuf::from(percent::decode(value));
+                    path = Some(match top {
+                        Some(top) if value.is_relative() => top.join(value),
+                        _ => value
+                    });
+                },
+                Some(("DeletionDate", value)) => deletion_date = DateTime::parse_rfc3339(value),
+                _ => {}
+            }
+        }
+
+        Some(TrashInfo { path: path?, deletion_date })
+    }
+
+    pub fn format(&self, top: Option<&Path>) -> String {
+        // paths in a mount's own trash are relative to its top directory
+        let path = match top.and_then(|top| self.path.strip_prefix(top).ok()) {
+            Some(relative) => relative,
+            None => &self.path
+        };
+        let mut contents = format!("[Trash Info]\nPath={}\n", percent::encode(&path.to_string_lossy()));
+        if let Some(deletion_date) = self.deletion_date {
+            contents.push_str(&format!("DeletionDate={}\n", deletion_date.to_rfc3339()));
+        }
+        contents
+    }
+}
+
+/// Undoes the octal escapes (e.g. `\040` for a space) used in `/proc/self/mounts`.
+#[cfg(target_os = "linux")]
+fn unescape_mount_point(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(i) = rest.find('\\') {
+        unescaped.push_str(&rest[..i]);
+        match rest.get(i + 1..i + 4).and_then(|octal| u8::from_str_radix(octal, 8).ok()) {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[i + 4..];
+            },
+            None => {
+                unescaped.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(target_os = "linux")]
+fn mount_points() -> Vec<PathBuf> {
+    fs::read_to_string("/proc/self/mounts")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|field| PathBuf::from(unescape_mount_point(field)))
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn mount_points() -> Vec<PathBuf> {
+    vec![PathBuf::from("/")]
+}
+
+/// The top directory of the mount `path` is on, i.e. its highest ancestor on the same device.
+#[cfg(unix)]
+fn mount_top(path: &Path, device: u64) -> PathBuf {
+    let mut top = path;
+    while let Some(parent) = top.parent() {
+        match fs::metadata(parent) {
+            Ok(metadata) if metadata.dev() == device => top = parent,
+            _ => break
+        }
+    }
+    top.to_path_buf()
+}
+
+/// Chooses (and creates) the trash directory for `path`: the home trash if it's on the same
+/// device, otherwise the trash at the top of `path`'s own mount.
+#[cfg(unix)]
+fn trash_dir_for(path: &Path) -> io::Result<TrashDir> {
+    let device = fs::symlink_metadata(path)?.dev();
+
+    if let Some(home) = TrashDir::home()
+        && home.create().is_ok()
+        && fs::metadata(&home.root).is_ok_and(|metadata| metadata.dev() == device) {
+        return Ok(home);
+    }
+
+    let parent = path.parent().unwrap_or(path);
+    let mut last_error = io::Error::new(ErrorKind::NotFound, "no usable trash directory");
+    for trash_dir in TrashDir::for_top(&mount_top(parent, device)) {
+        match trash_dir.create() {
+            Ok(_) => return Ok(trash_dir),
+            Err(err) => last_error = err
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(not(unix))]
+fn trash_dir_for(_path: &Path) -> io::Result<TrashDir> {
+    Err(io::Error::new(ErrorKind::Unsupported, "the trash is only supported on unix"))
+}
+
+/// Moves `path` into the trash, returning where it ended up.
+pub fn put(path: &Path) -> io::Result<PathBuf> {
+    let path = path::absolute(path)?;
+    let file_name = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => return Err(io::Error::new(ErrorKind::InvalidInput, "cannot trash a root directory"))
+    };
+    let trash_dir = trash_dir_for(&path)?;
+
+    let info = TrashInfo {
+        path: path.clone(),
+        deletion_date: Some(DateTime::local(SystemTime::now()))
+    };
+    let contents = info.format(trash_dir.top.as_deref());
+
+    // creating the .trashinfo file exclusively reserves the name
+    for n in 1.. {
+        let name = if n == 1 {
+            file_name.clone()
+        } else {
+            format!("{}.{}", file_name, n)
+        };
+        let info_path = trash_dir.info().join(format!("{}.trashinfo", name));
+        let destination = trash_dir.files().join(&name);
+        if fs::symlink_metadata(&destination).is_ok() {
+            continue;
+        }
+
+        let mut info_file = match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err)
+        };
+        let moved = info_file.write_all(contents.as_bytes())
+            .and_then(|_| fs::rename(&path, &destination));
+        if let Err(err) = moved {
+            let _ = fs::remove_file(&info_path);
+            return Err(err);
+        }
+        return Ok(destination);
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATE: DateTime = DateTime { year: 2025, month: 3, day: 14, hour: 15, minute: 9, second: 26 };
+
+    #[test]
+    fn info_round_trip() {
+        let info = TrashInfo {
+            path: PathBuf::from("/home/me/100% done: notes.txt"),
+            deletion_date: Some(DATE)
+        };
+        let contents = info.format(None);
+        assert_eq!(contents, "[Trash Info]\nPath=/home/me/100%25%20done:%20notes.txt\nDeletionDate=2025-03-14T15:09:26\n");
+        let parsed = TrashInfo::parse(&contents, None).unwrap();
+        assert_eq!(parsed.path, info.path);
+        assert_eq!(parsed.deletion_date, info.deletion_date);
+    }
+
+    #[test]
+    fn info_paths_are_relative_to_the_top_of_a_mount() {
+        let info = TrashInfo {
+            path: PathBuf::from("/mnt/usb/photos/cat.jpg"),
+            deletion_date: None
+        };
+        let top = Path::new("/mnt/usb");
+        let contents = info.format(Some(top));
+        assert_eq!(contents, "[Trash Info]\nPath=photos/cat.jpg\n");
+        assert_eq!(TrashInfo::parse(&contents, Some(top)).unwrap().path, info.path);
+        // absolute paths are allowed there too
+        let absolute = TrashInfo::parse("[Trash Info]\nPath=/mnt/usb/a\n", Some(top)).unwrap();
+        assert_eq!(absolute.path, PathBuf::from("/mnt/usb/a"));
+    }
+
+    #[test]
+    fn info_parsing_is_lenient_about_extras() {
+        let info = TrashInfo::parse("  [Trash Info]  \nUnknown=1\nnonsense\n Path=/a%2Fb \n", None).unwrap();
+        assert_eq!(info.path, PathBuf::from("/a/b"));
+        assert_eq!(info.deletion_date, None);
+    }
+
+    #[test]
+    fn malformed_info_is_rejected() {
+        assert!(TrashInfo::parse("", None).is_none());
+        assert!(TrashInfo::parse("Path=/a\n", None).is_none());
+        assert!(TrashInfo::parse("[Desktop Entry]\nPath=/a\n", None).is_none());
+        assert!(TrashInfo::parse("[Trash Info]\nDeletionDate=2025-03-14T15:09:26\n", None).is_none());
+        let undated = TrashInfo::parse("[Trash Info]\nPath=/a\nDeletionDate=yesterday\n", None).unwrap();
+        assert_eq!(undated.deletion_date, None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mount_points_are_unescaped() {
+        assert_eq!(unescape_mount_point("/media/my\\040disk"), "/media/my disk");
+        assert_eq!(unescape_mount_point("/a\\011b\\134c"), "/a\tb\\c");
+        assert_eq!(unescape_mount_point("/odd\\9"), "/odd\\9");
+    }
+}
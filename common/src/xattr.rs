@@ -0,0 +1,112 @@
+//! Extended attributes (and so ACLs and SELinux contexts), read and written without following symlinks.
+//! Only Linux has them here; elsewhere every call fails with `Unsupported`.
+
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    path::Path
+};
+
+#[cfg(target_os = "linux")]
+use std::{
+    ffi::CString,
+    os::unix::ffi::{OsStrExt, OsStringExt}
+};
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn llistxattr(path: *const u8, list: *mut u8, size: usize) -> isize;
+    fn lgetxattr(path: *const u8, name: *const u8, value: *mut u8, size: usize) -> isize;
+    fn lsetxattr(path: *const u8, name: *const u8, value: *const u8, size: usize, flags: i32) -> i32;
+}
+
+/// The buffer was too small for the list or value.
+#[cfg(target_os = "linux")]
+const ERANGE: i32 = 34;
+
+#[cfg(target_os = "linux")]
+fn c_string(text: &OsStr) -> io::Result<CString> {
+    Ok(CString::new(text.as_bytes())?)
+}
+
+/// Queries the size needed with `call(null, 0)` and then reads into a buffer that big. The list or
+/// value can grow between the two calls, so that (and only that) is retried.
+#[cfg(target_os = "linux")]
+fn read(call: impl Fn(*mut u8, usize) -> isize) -> io::Result<Vec<u8>> {
+    loop {
+        let size = call(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; size as usize];
+        let size = call(buffer.as_mut_ptr(), buffer.len());
+        if size >= 0 {
+            buffer.truncate(size as usize);
+            return Ok(buffer);
+        }
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(ERANGE) {
+            return Err(err);
+        }
+    }
+}
+
+/// The names of the extended attributes of `path`.
+#[cfg(target_os = "linux")]
+pub fn list(path: &Path) -> io::Result<Vec<OsString>> {
+    let path = c_string(path.as_os_str())?;
+    // SAFETY: the path is a valid C string and `read` passes a buffer of the size given (or null and 0)
+    let names = read(|buffer, size| unsafe { llistxattr(path.as_ptr().cast(), buffer, size) })?;
+    Ok(names.split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| OsString::from_vec(name.to_vec()))
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn list(_path: &Path) -> io::Result<Vec<OsString>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// The size of the value of the extended attribute `name` of `path`, without reading it.
+#[cfg(target_os = "linux")]
+pub fn size(path: &Path, name: &OsStr) -> io::Result<usize> {
+    let (path, name) = (c_string(path.as_os_str())?, c_string(name)?);
+    // SAFETY: both strings are valid C strings and a null buffer with size 0 only queries the size
+    let size = unsafe { lgetxattr(path.as_ptr().cast(), name.as_ptr().cast(), std::ptr::null_mut(), 0) };
+    usize::try_from(size).map_err(|_| io::Error::last_os_error())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn size(_path: &Path, _name: &OsStr) -> io::Result<usize> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// The value of the extended attribute `name` of `path`.
+#[cfg(target_os = "linux")]
+pub fn get(path: &Path, name: &OsStr) -> io::Result<Vec<u8>> {
+    let (path, name) = (c_string(path.as_os_str())?, c_string(name)?);
+    // SAFETY: both strings are valid C strings and `read` passes a buffer of the size given (or null and 0)
+    read(|buffer, size| unsafe { lgetxattr(path.as_ptr().cast(), name.as_ptr().cast(), buffer, size) })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get(_path: &Path, _name: &OsStr) -> io::Result<Vec<u8>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Sets the extended attribute `name` of `path` to `value`, creating it if need be.
+#[cfg(target_os = "linux")]
+pub fn set(path: &Path, name: &OsStr, value: &[u8]) -> io::Result<()> {
+    let (path, name) = (c_string(path.as_os_str())?, c_string(name)?);
+    // SAFETY: both strings are valid C strings and the size matches the value
+    if unsafe { lsetxattr(path.as_ptr().cast(), name.as_ptr().cast(), value.as_ptr(), value.len(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set(_path: &Path, _name: &OsStr, _value: &[u8]) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
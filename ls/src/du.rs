@@ -0,0 +1,107 @@
+use common::pool::Pool;
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fs::{self, Metadata},
+    path::PathBuf,
+    sync::Mutex,
+    thread
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+/// The number of bytes actually allocated on disk for a file.
+pub fn allocated(metadata: &Metadata) -> u64 {
+    #[cfg(unix)] {
+        metadata.blocks() * 512
+    }
+    #[cfg(not(unix))] {
+        metadata.len()
+    }
+}
+
+/// Identifies a file with more than one hard link, which mustn't be counted twice within a tree.
+fn hard_link(metadata: &Metadata) -> Option<(u64, u64)> {
+    #[cfg(unix)] {
+        if metadata.nlink() > 1 && !metadata.is_dir() {
+            return Some((metadata.dev(), metadata.ino()));
+        }
+    }
+    #[cfg(not(unix))] {
+        let _ = metadata;
+    }
+    None
+}
+
+/// The space taken up by a directory and everything below it that has been added to it so far.
+struct Usage {
+    size: u64,
+    /// hard linked files by device and inode, so each is only counted once
+    links: HashMap<(u64, u64), u64>
+}
+
+impl Usage {
+    fn total(&self) -> u64 {
+        self.size + self.links.values().sum::<u64>()
+    }
+}
+
+/// Computes the total allocated size of every directory in the trees `roots`, by path.
+///
+/// Each directory is read once, by one of a pool of workers (one per available core), and its usage
+/// is then added to that of the directory above it. Symlinks are never followed and hard links are
+/// only counted once per tree.
+pub fn directory_totals(roots: &[PathBuf]) -> HashMap<PathBuf, u64> {
+    // each directory with only the files directly inside it
+    let usages: Mutex<HashMap<PathBuf, Usage>> = Mutex::new(HashMap::new());
+
+    let pool: Pool<(PathBuf, u64)> = Pool::new();
+    pool.extend(roots.iter().filter_map(|root| {
+        let metadata = fs::symlink_metadata(root).ok()?;
+        Some((root.clone(), allocated(&metadata)))
+    }));
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    pool.run(workers, |(directory, size)| {
+        let mut usage = Usage { size, links: HashMap::new() };
+        let mut subdirectories: Vec<(PathBuf, u64)> = Vec::new();
+        if let Ok(entries) = fs::read_dir(&directory) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue
+                };
+                if metadata.is_dir() {
+                    subdirectories.push((entry.path(), allocated(&metadata)));
+                } else if let Some(link) = hard_link(&metadata) {
+                    usage.links.insert(link, allocated(&metadata));
+                } else {
+                    usage.size += allocated(&metadata);
+                }
+            }
+        }
+        pool.extend(subdirectories);
+        usages.lock().unwrap().insert(directory, usage);
+    });
+
+    // deepest first, so every directory is complete before it's added to its parent
+    let mut usages = usages.into_inner().unwrap();
+    let mut directories: Vec<PathBuf> = usages.keys().cloned().collect();
+    directories.sort_by_key(|directory| Reverse(directory.components().count()));
+    let roots: HashSet<&PathBuf> = roots.iter().collect();
+
+    let mut totals: HashMap<PathBuf, u64> = HashMap::with_capacity(directories.len());
+    for directory in directories {
+        let Some(usage) = usages.remove(&directory) else {
+            continue;
+        };
+        totals.insert(directory.clone(), usage.total());
+        if !roots.contains(&directory)
+            && let Some(parent) = directory.parent().and_then(|parent| usages.get_mut(parent)) {
+            parent.size += usage.size;
+            parent.links.extend(usage.links);
+        }
+    }
+    totals
+}
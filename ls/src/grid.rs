@@ -0,0 +1,158 @@
+use std::env;
+
+/// (first, last) code points of characters which take up two terminal cells.
+const WIDE: [(u32, u32); 47] = [
+    (0x1100, 0x115F),
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F3),
+    (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA),
+    (0x26FD, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274C),
+    (0x274E, 0x274E),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B50),
+    (0x2B55, 0x2B55),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE30, 0xFE4F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1F300, 0x1FAFF),
+    (0x20000, 0x3FFFD)
+];
+
+/// (first, last) code points of characters which don't take up a terminal cell of their own.
+const ZERO_WIDTH: [(u32, u32); 7] = [
+    (0x0300, 0x036F),
+    (0x200B, 0x200F),
+    (0x20D0, 0x20FF),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+    (0xE0100, 0xE01EF),
+    (0x1F3FB, 0x1F3FF)
+];
+
+fn char_width(ch: char) -> usize {
+    let code = ch as u32;
+    let within = |ranges: &[(u32, u32)]| ranges.iter()
+        .any(|&(first, last)| (first..=last).contains(&code));
+    if ch.is_control() || within(&ZERO_WIDTH) {
+        0
+    } else if within(&WIDE) {
+        2
+    } else {
+        // includes the private use area nerd font icons live in
+        1
+    }
+}
+
+/// The number of terminal cells `text` occupies.
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// The width of the terminal stdout is connected to, `80` if it can't be determined.
+pub fn terminal_width() -> usize {
+    if let Some(columns) = env::var("COLUMNS").ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0) {
+        return columns;
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))] {
+        #[repr(C)]
+        struct WinSize {
+            ws_row: u16,
+            ws_col: u16,
+            ws_xpixel: u16,
+            ws_ypixel: u16
+        }
+
+        unsafe extern "C" {
+            fn ioctl(fd: i32, request: u64, ...) -> i32;
+        }
+
+        #[cfg(target_os = "linux")]
+        const TIOCGWINSZ: u64 = 0x5413;
+        #[cfg(target_os = "macos")]
+        const TIOCGWINSZ: u64 = 0x4008_7468;
+
+        let mut size = WinSize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+        // SAFETY: TIOCGWINSZ only writes a `winsize` to the pointer
+        if unsafe { ioctl(1, TIOCGWINSZ, &mut size as *mut WinSize) } == 0 && size.ws_col > 0 {
+            return size.ws_col as usize;
+        }
+    }
+
+    80
+}
+
+/// Fits cells of the given widths into as many columns as `line_width` allows,
+/// filling each column top to bottom like GNU `ls -C`.
+///
+/// Returns the number of rows and the width of each column (including the gap after it).
+pub fn layout(widths: &[usize], line_width: usize) -> (usize, Vec<usize>) {
+    const GAP: usize = 2;
+
+    for columns in (1..=widths.len()).rev() {
+        let rows = widths.len().div_ceil(columns);
+        // the last few columns can end up empty, e.g. 4 cells in 3 columns needs 2 rows
+        if (columns - 1) * rows >= widths.len() {
+            continue;
+        }
+
+        let column_widths: Vec<usize> = widths.chunks(rows)
+            .map(|column| column.iter().max().unwrap_or(&0) + GAP)
+            .collect();
+        if columns == 1 || column_widths.iter().sum::<usize>() - GAP <= line_width {
+            return (rows, column_widths);
+        }
+    }
+
+    (0, Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本"), 4);
+        assert_eq!(display_width("⚡✅⭐"), 6);
+        assert_eq!(display_width("☀✓"), 2);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("👍🏽"), 2);
+    }
+}
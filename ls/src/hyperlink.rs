@@ -0,0 +1,47 @@
+use common::percent;
+
+use std::{
+    path::{self, Path},
+    sync::OnceLock
+};
+
+fn hostname() -> &'static str {
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    HOSTNAME.get_or_init(|| {
+        #[cfg(unix)] {
+            unsafe extern "C" {
+                fn gethostname(name: *mut u8, len: usize) -> i32;
+            }
+
+            let mut buffer = [0u8; 256];
+            // SAFETY: the length passed matches the buffer
+            if unsafe { gethostname(buffer.as_mut_ptr(), buffer.len()) } != 0 {
+                return String::new();
+            }
+            let len = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+            String::from_utf8_lossy(&buffer[..len]).to_string()
+        }
+        #[cfg(not(unix))] {
+            std::env::var("COMPUTERNAME").unwrap_or_default()
+        }
+    })
+}
+
+fn file_url(path: &Path) -> String {
+    let absolute = path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut absolute = absolute.to_string_lossy().replace('\\', "/");
+    if !absolute.starts_with('/') {
+        // windows drive paths (`C:/...`)
+        absolute.insert(0, '/');
+    }
+    format!("file://{}{}", percent::encode(hostname()), percent::encode(&absolute))
+}
+
+/// Opens an OSC 8 hyperlink to `path`, everything printed until `end()` becomes clickable.
+pub fn start(path: &Path) -> String {
+    format!("\x1b]8;;{}\x1b\\", file_url(path))
+}
+
+pub fn end() -> &'static str {
+    "\x1b]8;;\x1b\\"
+}
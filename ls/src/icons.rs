@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    env
+};
+
+/// File names with an icon of their own.
+const NAMES: [(&str, char); 16] = [
+    (".bashrc", '\u{f489}'),
+    (".git", '\u{e5fb}'),
+    (".gitattributes", '\u{f1d3}'),
+    (".gitignore", '\u{f1d3}'),
+    (".gitmodules", '\u{f1d3}'),
+    ("Cargo.lock", '\u{e7a8}'),
+    ("Cargo.toml", '\u{e7a8}'),
+    ("docker-compose.yml", '\u{f308}'),
+    ("Dockerfile", '\u{f308}'),
+    ("LICENSE", '\u{f02d}'),
+    ("Makefile", '\u{f489}'),
+    ("node_modules", '\u{e5fa}'),
+    ("package.json", '\u{e71e}'),
+    ("README.md", '\u{f48a}'),
+    ("requirements.txt", '\u{e606}'),
+    ("target", '\u{f487}')
+];
+
+/// File extensions (lowercase) with an icon of their own.
+const EXTENSIONS: [(&str, char); 40] = [
+    ("7z", '\u{f410}'),
+    ("bat", '\u{f489}'),
+    ("c", '\u{e61e}'),
+    ("cpp", '\u{e61d}'),
+    ("cs", '\u{f031b}'),
+    ("css", '\u{e749}'),
+    ("csv", '\u{f1c3}'),
+    ("exe", '\u{f489}'),
+    ("gif", '\u{f1c5}'),
+    ("go", '\u{e626}'),
+    ("gz", '\u{f410}'),
+    ("h", '\u{f0fd}'),
+    ("hpp", '\u{f0fd}'),
+    ("html", '\u{f13b}'),
+    ("java", '\u{e738}'),
+    ("jpeg", '\u{f1c5}'),
+    ("jpg", '\u{f1c5}'),
+    ("js", '\u{e74e}'),
+    ("json", '\u{e60b}'),
+    ("lock", '\u{f023}'),
+    ("lua", '\u{e620}'),
+    ("md", '\u{f48a}'),
+    ("mp3", '\u{f001}'),
+    ("mp4", '\u{f03d}'),
+    ("pdf", '\u{f1c1}'),
+    ("png", '\u{f1c5}'),
+    ("py", '\u{e606}'),
+    ("rb", '\u{e21e}'),
+    ("rs", '\u{e7a8}'),
+    ("sh", '\u{f489}'),
+    ("sql", '\u{f1c0}'),
+    ("svg", '\u{f1c5}'),
+    ("tar", '\u{f410}'),
+    ("toml", '\u{e6b2}'),
+    ("ts", '\u{e628}'),
+    ("txt", '\u{f15c}'),
+    ("xml", '\u{f05c0}'),
+    ("yaml", '\u{e6a8}'),
+    ("yml", '\u{e6a8}'),
+    ("zip", '\u{f410}')
+];
+
+const DIRECTORY: char = '\u{e5ff}';
+const FILE: char = '\u{f15b}';
+const SYMLINK: char = '\u{f481}';
+const EXECUTABLE: char = '\u{f489}';
+
+pub enum Kind {
+    Directory,
+    Symlink,
+    Executable,
+    File
+}
+
+/// Icons by file type, extension and file name.
+///
+/// The defaults can be overridden through `LS_ICONS`, which uses the same
+/// format as `LS_COLORS`: `key=icon` pairs separated by colons, where a key
+/// is `di`, `ln`, `ex` or `fi` for a file type, `*.ext` for an extension
+/// or otherwise an exact file name, e.g. `LS_ICONS='*.rs=🦀:Makefile=🔨'`.
+pub struct Icons {
+    names: HashMap<String, String>,
+    extensions: HashMap<String, String>,
+    directory: String,
+    symlink: String,
+    executable: String,
+    file: String
+}
+
+impl Icons {
+    pub fn load() -> Self {
+        let mut icons = Icons {
+            names: NAMES.iter()
+                .map(|(name, icon)| (name.to_string(), icon.to_string()))
+                .collect(),
+            extensions: EXTENSIONS.iter()
+                .map(|(extension, icon)| (extension.to_string(), icon.to_string()))
+                .collect(),
+            directory: DIRECTORY.to_string(),
+            symlink: SYMLINK.to_string(),
+            executable: EXECUTABLE.to_string(),
+            file: FILE.to_string()
+        };
+
+        if let Ok(config) = env::var("LS_ICONS") {
+            for (key, icon) in config.split(':').filter_map(|pair| pair.split_once('=')) {
+                let icon = icon.to_string();
+                match key {
+                    "di" => icons.directory = icon,
+                    "ln" => icons.symlink = icon,
+                    "ex" => icons.executable = icon,
+                    "fi" => icons.file = icon,
+                    key => match key.strip_prefix("*.") {
+                        Some(extension) => {
+                            icons.extensions.insert(extension.to_lowercase(), icon);
+                        },
+                        None => {
+                            icons.names.insert(key.to_string(), icon);
+                        }
+                    }
+                }
+            }
+        }
+
+        icons
+    }
+
+    pub fn icon(&self, file_name: &str, kind: Kind) -> &str {
+        if let Some(icon) = self.names.get(file_name) {
+            return icon;
+        }
+        match kind {
+            Kind::Directory => return &self.directory,
+            Kind::Symlink => return &self.symlink,
+            Kind::Executable | Kind::File => {}
+        }
+        if let Some((_, extension)) = file_name.rsplit_once('.')
+            && let Some(icon) = self.extensions.get(&extension.to_lowercase()) {
+            return icon;
+        }
+        match kind {
+            Kind::Executable => &self.executable,
+            _ => &self.file
+        }
+    }
+}
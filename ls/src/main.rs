@@ -1,14 +1,76 @@
+use common::{
+    help::{Opt, Tool},
+    time::{unix_seconds, DateTime}
+};
+use icons::Icons;
+use output::Output;
+use walk::Walker;
+use watch::{Snapshot, Watcher};
+
 use std::{
+    collections::{HashMap, HashSet},
     env,
-    fs::{read_dir, metadata, Metadata},
+    fs::{read_dir, read_link, metadata, Metadata},
+    io::{ErrorKind, IsTerminal, stdout},
     path::{
         Path,
         PathBuf
-    }
+    },
+    thread,
+    time::SystemTime
 };
 
 #[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
+use std::{
+    fs,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    sync::OnceLock
+};
+
+mod du;
+mod grid;
+mod hyperlink;
+mod icons;
+mod output;
+mod walk;
+mod watch;
+mod xattr;
+
+const LS: Tool = Tool {
+    name: "ls",
+    version: env!("CARGO_PKG_VERSION"),
+    usage: "[OPTION]... [FILE]...",
+    description: "List information about the FILEs (the current directory by default).",
+    options: &[
+        Opt::new(&['a'], "all", "do not ignore entries starting with ."),
+        Opt::new(&['C'], "", "list entries by columns"),
+        Opt::new(&['D'], "dired", "generate output designed for Emacs' dired mode; implies -l"),
+        Opt::new(&[], "du", "show and sort by the disk usage of whole directory trees rather than the size \
+            of the directory itself"),
+        Opt::new(&[], "group-directories-first", "group directories before files"),
+        Opt::new(&[], "hyperlink", "hyperlink file names; WHEN is always (default), auto or never").value("[=WHEN]"),
+        Opt::new(&[], "icons", "print an icon next to each file name; WHEN is always (default), auto or never \
+            (see below)").value("[=WHEN]"),
+        Opt::new(&['l'], "", "use a long listing format"),
+        Opt::new(&['m'], "", "fill width with a comma separated list of entries"),
+        Opt::new(&['Q'], "quote-name", "enclose entry names in double quotes"),
+        Opt::new(&['r'], "reverse", "reverse order while sorting"),
+        Opt::new(&['R'], "recursive", "list subdirectories recursively"),
+        Opt::new(&['S'], "", "sort by file size, largest first"),
+        Opt::new(&[], "watch", "redraw the listing whenever something in it changes, highlighting what changed"),
+        Opt::new(&[], "xattr", "list the extended attributes of each file below it; implies -l"),
+        Opt::new(&['Z'], "context", "print any security context of each file"),
+        Opt::new(&[], "zero", "end each output line with NUL, not newline")
+    ],
+    notes: "Icons can be overridden through the LS_ICONS environment variable, which uses
+the same format as LS_COLORS, e.g. LS_ICONS='*.rs=🦀:Makefile=🔨'."
+};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"
+];
+const SIX_MONTHS: i64 = 15_778_476;
 
 struct LSOpts {
     all: bool,
@@ -16,112 +78,621 @@ struct LSOpts {
     reverse: bool,
     group_directories_first: bool,
     recursive: bool,
-    quote_name: bool
+    quote_name: bool,
+    long: bool,
+    sort_size: bool,
+    du: bool,
+    hyperlink: bool,
+    context: bool,
+    xattr: bool,
+    zero: bool,
+    dired: bool,
+    colour: bool,
+    columns: bool,
+    icons: Option<Icons>,
+    watch: bool,
+    /// entries to draw attention to, i.e. what changed since the last `--watch` redraw
+    highlight: HashSet<PathBuf>
 }
 
-fn ls(directory: &Path, options: &LSOpts) {
-    if !directory.exists() {
-        println!("\x1b[0;91mError: Path not found '{}'.\x1b[0m", directory.to_string_lossy());
-        return;
+impl LSOpts {
+    /// What ends each line (or each name with `--zero`).
+    fn eol(&self) -> &'static str {
+        if self.zero { "\0" } else { "\n" }
     }
 
-    let mut paths: Vec<PathBuf> = read_dir(directory).unwrap()
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.path())
-        .collect();
+    /// What goes between the listings of two directories.
+    fn separator(&self) -> &'static str {
+        if self.long || self.zero || self.columns {
+            // the last line has already been ended
+            self.eol()
+        } else {
+            "\n\n"
+        }
+    }
 
-    if options.group_directories_first {
-        paths.sort_by(|a, b| {
-            b.is_dir()
-                .cmp(&a.is_dir())
-                .then_with(|| a.cmp(b))
+    /// `--dired` indents every line of a listing by two spaces.
+    fn line_prefix(&self) -> &'static str {
+        if self.dired { "  " } else { "" }
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    file_name: String,
+    is_dir: bool,
+    is_symlink: bool,
+    /// the entry couldn't be `stat`ed although it had to be (e.g. a dangling symlink)
+    missing: bool,
+    metadata: Option<Metadata>,
+    size: u64,
+    xattrs: Vec<(String, usize)>,
+    context: Option<String>
+}
+
+#[cfg(unix)]
+fn read_id_names(file: &str) -> HashMap<u32, String> {
+    fs::read_to_string(file)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> String {
+    static USERS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    match USERS.get_or_init(|| read_id_names("/etc/passwd")).get(&uid) {
+        Some(name) => name.clone(),
+        None => uid.to_string()
+    }
+}
+
+#[cfg(unix)]
+fn group_name(gid: u32) -> String {
+    static GROUPS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    match GROUPS.get_or_init(|| read_id_names("/etc/group")).get(&gid) {
+        Some(name) => name.clone(),
+        None => gid.to_string()
+    }
+}
+
+#[cfg(unix)]
+fn mode_string(metadata: &Metadata) -> String {
+    let mode = metadata.mode();
+    let mut mode_string = String::with_capacity(10);
+    mode_string.push(match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-'
+    });
+    // (shift, special bit, special character)
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        mode_string.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        mode_string.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        mode_string.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-'
         });
-    } else {
-        paths.sort();
     }
+    mode_string
+}
 
-    if options.reverse {
-        paths.reverse();
+#[cfg(not(unix))]
+fn mode_string(metadata: &Metadata) -> String {
+    let file_type = if metadata.is_dir() {
+        'd'
+    } else if metadata.is_symlink() {
+        'l'
+    } else {
+        '-'
+    };
+    let permissions = if metadata.permissions().readonly() { "r--" } else { "rw-" };
+    format!("{}{}{}{}", file_type, permissions, permissions, permissions)
+}
+
+fn format_time(time: SystemTime) -> String {
+    let seconds = unix_seconds(time);
+    let now = unix_seconds(SystemTime::now());
+    let local = DateTime::local(time);
+
+    if (now - seconds).abs() < SIX_MONTHS {
+        format!("{} {:>2} {:02}:{:02}",
+            MONTHS[local.month as usize - 1], local.day,
+            local.hour, local.minute)
+    } else {
+        format!("{} {:>2}  {}", MONTHS[local.month as usize - 1], local.day, local.year)
     }
+}
 
-    let mut to_recurse: Vec<PathBuf> = Vec::new();
+fn is_executable(entry: &Entry) -> bool {
+    if entry.is_dir {
+        return false;
+    }
+    #[cfg(unix)] {
+        entry.metadata.as_ref()
+            .is_some_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))] {
+        entry.path.extension().is_some_and(|extension| extension == "exe")
+    }
+}
 
-    for (i, path) in paths.iter().enumerate() {
-        let file_name = path
-            .file_name().unwrap()
-            .to_string_lossy();
+fn icon<'a>(entry: &Entry, icons: &'a Icons) -> &'a str {
+    let kind = if entry.is_symlink {
+        icons::Kind::Symlink
+    } else if entry.is_dir {
+        icons::Kind::Directory
+    } else if is_executable(entry) {
+        icons::Kind::Executable
+    } else {
+        icons::Kind::File
+    };
+    icons.icon(&entry.file_name, kind)
+}
 
-        if !path.exists() {
-            println!("\x1b[0;91mError: Path not found '{}'.\x1b[0m", file_name);
-            continue;
+fn print_name(entry: &Entry, options: &LSOpts, out: &mut Output) {
+    if options.colour {
+        if entry.is_dir {
+            out.write_str("\x1b[0;94m"); // intense blue
+        } else if is_executable(entry) {
+            out.write_str("\x1b[0;92m"); // intense green
         }
+    }
 
-        if !options.all && file_name.starts_with(".") {
-            continue;
-        }
+    let highlighted = options.highlight.contains(&entry.path);
+    if highlighted {
+        out.write_str("\x1b[1;7m"); // bold, reversed
+    }
+
+    if let Some(icons) = &options.icons {
+        write!(out, "{} ", icon(entry, icons));
+    }
+
+    if options.hyperlink {
+        out.write_str(&hyperlink::start(&entry.path));
+    }
+    if options.quote_name {
+        out.write_str("\"");
+    }
+    out.name(&entry.file_name);
+    if options.quote_name {
+        out.write_str("\"");
+    }
+    if options.hyperlink {
+        out.write_str(hyperlink::end());
+    }
+    if options.colour || highlighted {
+        out.write_str("\x1b[0m"); // reset colouring
+    }
+}
+
+fn print_long(entries: &[Entry], options: &LSOpts, out: &mut Output) {
+    struct Columns {
+        mode: String,
+        links: String,
+        user: String,
+        group: String,
+        context: String,
+        size: String,
+        time: String
+    }
+
+    // only pad the mode column for markers when at least one entry has one
+    let has_markers = entries.iter()
+        .any(|entry| xattr::mode_marker(&entry.xattrs).is_some());
 
-        let metadata: Metadata = metadata(path).unwrap();
-        if metadata.is_dir() {
-            if options.recursive {
-                to_recurse.append(&mut vec![path.clone()]);
+    let columns: Vec<Option<Columns>> = entries.iter()
+        .map(|entry| entry.metadata.as_ref().map(|metadata| Columns {
+            mode: match xattr::mode_marker(&entry.xattrs) {
+                Some(marker) => format!("{}{}", mode_string(metadata), marker),
+                None if has_markers => format!("{} ", mode_string(metadata)),
+                None => mode_string(metadata)
+            },
+            #[cfg(unix)]
+            links: metadata.nlink().to_string(),
+            #[cfg(unix)]
+            user: user_name(metadata.uid()),
+            #[cfg(unix)]
+            group: group_name(metadata.gid()),
+            #[cfg(not(unix))]
+            links: String::new(),
+            #[cfg(not(unix))]
+            user: String::new(),
+            #[cfg(not(unix))]
+            group: String::new(),
+            context: entry.context.clone().unwrap_or_else(|| "?".to_string()),
+            size: entry.size.to_string(),
+            time: metadata.modified().map_or_else(|_| "?".to_string(), format_time)
+        }))
+        .collect();
+
+    let width = |field: fn(&Columns) -> &String| columns.iter()
+        .flatten()
+        .map(|column| field(column).len())
+        .max()
+        .unwrap_or(0);
+    let links_width = width(|column| &column.links);
+    let user_width = width(|column| &column.user);
+    let group_width = width(|column| &column.group);
+    let context_width = width(|column| &column.context);
+    let size_width = width(|column| &column.size);
+
+    let total: u64 = entries.iter()
+        .map(|entry| match &entry.metadata {
+            Some(_) if options.du => entry.size,
+            Some(metadata) => du::allocated(metadata),
+            None => 0
+        })
+        .sum();
+    write!(out, "{}total {}{}", options.line_prefix(), total.div_ceil(1024), options.eol());
+
+    for (entry, column) in entries.iter().zip(columns) {
+        let (metadata, column) = match (&entry.metadata, column) {
+            (Some(metadata), Some(column)) => (metadata, column),
+            _ => {
+                out.error(&format!("Path not found '{}'.", entry.file_name));
+                continue;
             }
-            print!("{}", "\x1b[0;94m"); // intense blue
+        };
+
+        write!(out, "{}{} ", options.line_prefix(), column.mode);
+        if !column.links.is_empty() {
+            write!(out, "{:>width$} ", column.links, width = links_width);
+            write!(out, "{:<width$} ", column.user, width = user_width);
+            write!(out, "{:<width$} ", column.group, width = group_width);
+        }
+        if options.context {
+            write!(out, "{:<width$} ", column.context, width = context_width);
+        }
+        write!(out, "{:>width$} {} ", column.size, column.time, width = size_width);
+        print_name(entry, options, out);
+        if metadata.is_symlink()
+            && let Ok(target) = read_link(&entry.path) {
+            write!(out, " -> {}", target.to_string_lossy());
         }
+        out.write_str(options.eol());
 
-        #[cfg(unix)] {
-            if !metadata.is_dir() {
-                if metadata.permissions().mode() & 0o111 != 0 {
-                    print!("{}", "\x1b[0;92m"); // intense green
-                }
+        if options.xattr {
+            let name_width = entry.xattrs.iter()
+                .map(|(name, _)| name.len())
+                .max()
+                .unwrap_or(0);
+            for (name, size) in &entry.xattrs {
+                write!(out, "{}\t{:<width$}\t{:>4}{}",
+                    options.line_prefix(), name, size, options.eol(), width = name_width);
             }
         }
-        #[cfg(not(unix))] {
-            match path.extension() {
-                Some(extension) => {
-                    if extension == "exe" {
-                        print!("{}", "\x1b[0;92m"); // intense green
-                    }
-                },
-                None => {}
+    }
+}
+
+fn print_header(directory: &str, options: &LSOpts, out: &mut Output) {
+    out.write_str(options.line_prefix());
+    out.subdirectory(directory);
+    write!(out, ":{}", options.eol());
+}
+
+/// Whether every entry has to be `stat`ed, or the file type from the directory listing is enough.
+fn needs_metadata(options: &LSOpts) -> bool {
+    // unix needs the permission bits to find executables
+    options.long || options.sort_size || options.du
+        || (cfg!(unix) && (options.colour || options.icons.is_some()))
+}
+
+/// Reads and sorts the entries of a directory, ready to be printed. `totals` are the `--du` sizes of the
+/// directories inside it, if they have been worked out already.
+fn scan(directory: &Path, options: &LSOpts, totals: Option<&HashMap<PathBuf, u64>>) -> Result<Vec<Entry>, String> {
+    let dir_entries = read_dir(directory).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => format!("Path not found '{}'.", directory.to_string_lossy()),
+        _ => format!("Cannot open directory '{}': {}.", directory.to_string_lossy(), err)
+    })?;
+
+    let mut entries: Vec<Entry> = dir_entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|dir_entry| {
+            let file_name = dir_entry
+                .file_name()
+                .to_string_lossy()
+                .to_string();
+            if !options.all && file_name.starts_with('.') {
+                return None;
+            }
+            let path = dir_entry.path();
+
+            // the long format describes symlinks themselves rather than their targets,
+            // otherwise only symlinks need a `stat` to find out what they point to
+            let file_type = dir_entry.file_type().ok();
+            let metadata = if options.long {
+                dir_entry.metadata().ok()
+            } else if needs_metadata(options) || file_type.is_none_or(|file_type| file_type.is_symlink()) {
+                metadata(&path).ok()
+            } else {
+                None
+            };
+            let is_dir = match &metadata {
+                Some(metadata) => metadata.is_dir(),
+                None => file_type.is_some_and(|file_type| file_type.is_dir())
+            };
+            let is_symlink = file_type.is_some_and(|file_type| file_type.is_symlink());
+            let missing = metadata.is_none()
+                && (options.long || file_type.is_none_or(|file_type| file_type.is_symlink()));
+
+            let size = match &metadata {
+                Some(metadata) if options.du => du::allocated(metadata),
+                Some(metadata) => metadata.len(),
+                None => 0
+            };
+            let xattrs = if options.long {
+                xattr::list(&path)
+            } else {
+                Vec::new()
+            };
+            let context = if options.context {
+                xattr::security_context(&path)
+            } else {
+                None
+            };
+            Some(Entry { path, file_name, is_dir, is_symlink, missing, metadata, size, xattrs, context })
+        })
+        .collect();
+
+    if options.du {
+        let computed;
+        let totals = match totals {
+            Some(totals) => totals,
+            None => {
+                let roots: Vec<PathBuf> = entries.iter()
+                    .filter(|entry| entry.is_dir)
+                    .map(|entry| entry.path.clone())
+                    .collect();
+                computed = du::directory_totals(&roots);
+                &computed
+            }
+        };
+        for entry in entries.iter_mut().filter(|entry| entry.is_dir) {
+            if let Some(&total) = totals.get(&entry.path) {
+                entry.size = total;
             }
         }
-        
-        if options.quote_name {
-            print!("\"");
+    }
+
+    if options.sort_size {
+        entries.sort_by(|a, b| {
+            b.size
+                .cmp(&a.size)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    } else {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    if options.group_directories_first {
+        entries.sort_by_key(|entry| !entry.is_dir);
+    }
+
+    if options.reverse {
+        entries.reverse();
+    }
+
+    Ok(entries)
+}
+
+/// The subdirectories `-R` descends into, in the order they're printed.
+///
+/// Symlinks are never followed, they could lead back up the tree.
+fn subdirectories(entries: &[Entry]) -> Vec<PathBuf> {
+    entries.iter()
+        .filter(|entry| entry.is_dir && !entry.is_symlink && !entry.missing)
+        .map(|entry| entry.path.clone())
+        .collect()
+}
+
+/// Prints an entry of a short listing, i.e. its security context and name.
+fn print_cell(entry: &Entry, options: &LSOpts, out: &mut Output) {
+    if options.context {
+        write!(out, "{} ", entry.context.as_deref().unwrap_or("?"));
+    }
+    print_name(entry, options, out);
+}
+
+/// The number of terminal cells `print_cell()` takes up for an entry.
+fn cell_width(entry: &Entry, options: &LSOpts) -> usize {
+    let mut width = grid::display_width(&entry.file_name);
+    if options.context {
+        width += grid::display_width(entry.context.as_deref().unwrap_or("?")) + 1;
+    }
+    if let Some(icons) = &options.icons {
+        width += grid::display_width(icon(entry, icons)) + 1;
+    }
+    if options.quote_name {
+        width += 2;
+    }
+    width
+}
+
+fn print_grid(entries: &[Entry], options: &LSOpts, out: &mut Output) {
+    for entry in entries.iter().filter(|entry| entry.missing) {
+        out.error(&format!("Path not found '{}'.", entry.file_name));
+    }
+    let entries: Vec<&Entry> = entries.iter()
+        .filter(|entry| !entry.missing)
+        .collect();
+
+    let widths: Vec<usize> = entries.iter()
+        .map(|entry| cell_width(entry, options))
+        .collect();
+    let (rows, column_widths) = grid::layout(&widths, grid::terminal_width());
+
+    for row in 0..rows {
+        for (column, column_width) in column_widths.iter().enumerate() {
+            let i = column * rows + row;
+            if i >= entries.len() {
+                break;
+            }
+            print_cell(entries[i], options, out);
+            if i + rows < entries.len() {
+                write!(out, "{:width$}", "", width = column_width - widths[i]);
+            }
         }
-        print!("{}", file_name);
-        if options.quote_name {
-            print!("\"");
+        out.write_str(options.eol());
+    }
+}
+
+fn print_entries(entries: &[Entry], options: &LSOpts, out: &mut Output) {
+    if options.long {
+        print_long(entries, options, out);
+        return;
+    }
+
+    if options.columns {
+        print_grid(entries, options, out);
+        return;
+    }
+
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.missing {
+            out.error(&format!("Path not found '{}'.", entry.file_name));
+            continue;
         }
-        print!("{}", "\x1b[0m"); // reset colouring
 
-        if options.comma_separated && i != paths.len() - 1 {
-            print!(", ");
+        print_cell(entry, options, out);
+
+        if options.zero {
+            out.write_str("\0");
+        } else if options.comma_separated && i != entries.len() - 1 {
+            out.write_str(", ");
         } else {
-            print!(" ");
+            out.write_str(" ");
         }
     }
+}
+
+/// Prints the listing of `directory` and then (in order) every subdirectory below it.
+fn print_tree(directory: &Path, walker: &Walker<Result<Vec<Entry>, String>>, options: &LSOpts, out: &mut Output) {
+    let entries = match walker.take(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            out.error(&err);
+            return;
+        }
+    };
+    print_entries(&entries, options, out);
+
+    let separator = options.separator();
+    let to_recurse = subdirectories(&entries);
+    drop(entries);
 
-    if to_recurse.len() > 0 {
-        print!("\n\n");
+    if !to_recurse.is_empty() {
+        out.write_str(separator);
     }
     for (i, path) in to_recurse.iter().enumerate() {
-        let path_name = path.to_string_lossy();
+        print_header(&path.to_string_lossy(), options, out);
+        print_tree(path, walker, options, out);
 
-        if !path.exists() {
-            println!("\x1b[0;91mError: Path not found '{}'.\x1b[0m", path_name);
-            continue;
+        if i != to_recurse.len() - 1 {
+            out.write_str(separator);
         }
+    }
+}
 
-        println!("{}:", path_name);
-        ls(&path, options);
+fn ls(directory: &Path, options: &LSOpts, out: &mut Output) {
+    if !options.recursive {
+        match scan(directory, options, None) {
+            Ok(entries) => print_entries(&entries, options, out),
+            Err(err) => out.error(&err)
+        }
+        return;
+    }
 
-        if i != to_recurse.len() - 1 {
-            print!("\n\n");
+    // the sizes of the whole tree in one go, rather than of every subtree again at each level
+    let totals = options.du.then(|| du::directory_totals(&[directory.to_path_buf()]));
+    let totals = totals.as_ref();
+
+    // directories are read and stat'ed in parallel, while this thread prints them in order
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let walker = Walker::new(directory.to_path_buf());
+    thread::scope(|scope| {
+        let walker = &walker;
+        for _ in 0..workers {
+            scope.spawn(move || walker.run(|directory| {
+                let entries = scan(directory, options, totals);
+                let subdirectories = match &entries {
+                    Ok(entries) => subdirectories(entries),
+                    Err(_) => Vec::new()
+                };
+                (entries, subdirectories)
+            }));
+        }
+        print_tree(directory, walker, options, out);
+    });
+}
+
+/// Lists every directory operand (or the current directory).
+fn list(directories: &[String], options: &LSOpts, out: &mut Output) {
+    let separator = options.separator();
+
+    if directories.is_empty() {
+        ls(Path::new("./"), options, out);
+    } else if directories.len() == 1 {
+        ls(Path::new(&directories[0]), options, out);
+    } else {
+        for (i, directory) in directories.iter().enumerate() {
+            let path: &Path = Path::new(&directory);
+            if !path.exists() {
+                out.error(&format!("Path not found '{}'.", path.to_string_lossy()));
+                continue;
+            }
+
+            print_header(directory, options, out);
+            ls(path, options, out);
+
+            if i != directories.len() - 1 {
+                out.write_str(separator);
+            }
         }
     }
 }
 
+/// Redraws the listing every time something changes, highlighting what changed.
+fn watch(directories: &[String], mut options: LSOpts) -> ! {
+    let roots: Vec<PathBuf> = if directories.is_empty() {
+        vec![PathBuf::from("./")]
+    } else {
+        directories.iter().map(PathBuf::from).collect()
+    };
+    let (recursive, all) = (options.recursive, options.all);
+
+    let mut previous: Option<Snapshot> = None;
+    loop {
+        let snapshot = Snapshot::take(&roots, recursive, all);
+        let watcher = Watcher::new(&snapshot);
+        options.highlight = match &previous {
+            Some(previous) => snapshot.changed_since(previous).into_iter().collect(),
+            None => HashSet::new()
+        };
+
+        let mut out = Output::new(options.dired);
+        out.clear_screen();
+        list(directories, &options, &mut out);
+        out.finish(if options.quote_name { "c" } else { "literal" });
+
+        watcher.wait(|| Snapshot::take(&roots, recursive, all) != snapshot);
+        previous = Some(snapshot);
+    }
+}
+
 fn main() {
     let mut options = LSOpts {
         all: false,
@@ -129,7 +700,20 @@ fn main() {
         reverse: false,
         group_directories_first: false,
         recursive: false,
-        quote_name: false
+        quote_name: false,
+        long: false,
+        sort_size: false,
+        du: false,
+        hyperlink: false,
+        context: false,
+        xattr: false,
+        zero: false,
+        dired: false,
+        colour: true,
+        columns: false,
+        icons: None,
+        watch: false,
+        highlight: HashSet::new()
     };
 
     let mut directories: Vec<String> = Vec::new();
@@ -148,14 +732,66 @@ fn main() {
     for arg in expanded_args {
         match arg.as_str() {
             "-a" | "--all" => options.all = true,
-            "-m" => options.comma_separated = true,
+            "-D" | "--dired" => options.dired = true,
+            "--du" => options.du = true,
+            "-l" => options.long = true,
+            "-C" => {
+                options.columns = true;
+                options.comma_separated = false;
+            },
+            "-m" => {
+                options.comma_separated = true;
+                options.columns = false;
+            },
             "-r" | "--reverse" => options.reverse = true,
+            "-S" => options.sort_size = true,
             "--group-directories-first" =>
                 options.group_directories_first = true,
             "-R" | "--recursive" => options.recursive = true,
             "-Q" | "--quote-name" => options.quote_name = true,
+            "-Z" | "--context" => options.context = true,
+            "--xattr" => {
+                options.xattr = true;
+                options.long = true;
+            },
+            "--watch" => options.watch = true,
+            "--zero" => options.zero = true,
+            "--icons" => options.icons = Some(Icons::load()),
+            arg if arg.starts_with("--icons=") => {
+                match &arg["--icons=".len()..] {
+                    "always" | "yes" | "force" => options.icons = Some(Icons::load()),
+                    "never" | "no" | "none" => options.icons = None,
+                    "auto" | "tty" | "if-tty" => {
+                        options.icons = if stdout().is_terminal() { Some(Icons::load()) } else { None };
+                    },
+                    when => {
+                        println!("\x1b[0;91mError: Invalid argument '{}' for '--icons'.\x1b[0m", when);
+                        return;
+                    }
+                }
+            },
+            "--hyperlink" => options.hyperlink = true,
+            "--help" => {
+                print!("{}", LS.help());
+                return;
+            },
+            "--version" => {
+                print!("{}", LS.version());
+                return;
+            },
+            arg if arg.starts_with("--hyperlink=") => {
+                match &arg["--hyperlink=".len()..] {
+                    "always" | "yes" | "force" => options.hyperlink = true,
+                    "never" | "no" | "none" => options.hyperlink = false,
+                    "auto" | "tty" | "if-tty" => options.hyperlink = stdout().is_terminal(),
+                    when => {
+                        println!("\x1b[0;91mError: Invalid argument '{}' for '--hyperlink'.\x1b[0m", when);
+                        return;
+                    }
+                }
+            },
             arg if !arg.starts_with('-') && !arg.starts_with("--") =>
-                directories.append(&mut vec![arg.to_string()]),
+                directories.push(arg.to_string()),
             _ => {
                 println!("\x1b[0;91mError: Unknown argument '{}'.\x1b[0m", arg);
                 return;
@@ -163,27 +799,31 @@ fn main() {
         }
     }
 
-    if directories.len() == 0 {
-        ls(&Path::new("./"), &options);
-    } else if directories.len() == 1 {
-        ls(&Path::new(&directories[0]), &options);
-    } else {
-        for (i, directory) in directories.iter().enumerate() {
-            let path: &Path = Path::new(&directory);
-            if !path.exists() {
-                println!("\x1b[0;91mError: Path not found '{}'.\x1b[0m", path.to_string_lossy());
-                if i != directories.len() - 1 {
-                    print!("\n");
-                }
-                continue;
-            }
+    if options.dired && options.zero {
+        println!("\x1b[0;91mError: Options '--dired' and '--zero' are incompatible.\x1b[0m");
+        return;
+    }
+    if options.zero {
+        // names must come out exactly as they are
+        options.quote_name = false;
+        options.comma_separated = false;
+        options.columns = false;
+        options.colour = false;
+        options.icons = None;
+    }
+    if options.dired {
+        // Emacs parses the long format and doesn't understand escape sequences
+        options.long = true;
+        options.hyperlink = false;
+        options.colour = false;
+        options.icons = None;
+    }
 
-            println!("{}:", directory);
-            ls(path, &options);
-            
-            if i != directories.len() - 1 {
-                print!("\n\n");
-            }
-        }
+    if options.watch {
+        watch(&directories, options);
     }
+
+    let mut out = Output::new(options.dired);
+    list(&directories, &options, &mut out);
+    out.finish(if options.quote_name { "c" } else { "literal" });
 }
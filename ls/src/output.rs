@@ -0,0 +1,94 @@
+use std::{
+    fmt,
+    io::{BufWriter, Stdout, Write, stdout},
+    process
+};
+
+/// Buffered stdout that keeps track of how many bytes have been written,
+/// so `--dired` can report where each file name starts and ends.
+pub struct Output {
+    writer: BufWriter<Stdout>,
+    position: usize,
+    dired: Option<Vec<(usize, usize)>>,
+    subdired: Vec<(usize, usize)>
+}
+
+impl Output {
+    pub fn new(dired: bool) -> Self {
+        Output {
+            writer: BufWriter::new(stdout()),
+            position: 0,
+            dired: if dired { Some(Vec::new()) } else { None },
+            subdired: Vec::new()
+        }
+    }
+
+    pub fn write_str(&mut self, text: &str) {
+        if self.writer.write_all(text.as_bytes()).is_err() {
+            // most likely a closed pipe, nobody is listening anymore
+            process::exit(2);
+        }
+        self.position += text.len();
+    }
+
+    /// Allows `write!`/`writeln!` to be used without handling a `Result`.
+    pub fn write_fmt(&mut self, args: fmt::Arguments) {
+        match args.as_str() {
+            Some(text) => self.write_str(text),
+            None => self.write_str(&args.to_string())
+        }
+    }
+
+    /// Writes a file name, recording its byte range for `--dired`.
+    pub fn name(&mut self, name: &str) {
+        let start = self.position;
+        self.write_str(name);
+        if let Some(dired) = &mut self.dired {
+            dired.push((start, self.position));
+        }
+    }
+
+    /// Writes a directory header's name, recording its byte range for `--dired`.
+    pub fn subdirectory(&mut self, name: &str) {
+        let start = self.position;
+        self.write_str(name);
+        if self.dired.is_some() {
+            self.subdired.push((start, self.position));
+        }
+    }
+
+    /// Clears the terminal, which doesn't count towards `--dired` positions.
+    pub fn clear_screen(&mut self) {
+        self.write_str("\x1B[2J\x1B[1;1H");
+        self.position = 0;
+    }
+
+    /// Reports an error on stderr, so it can't end up among the names of `--zero` or `--dired`.
+    /// Whatever was printed before it is flushed first to keep the two in order on a terminal.
+    pub fn error(&mut self, message: &str) {
+        self.flush();
+        eprintln!("\x1b[0;91mError: {}\x1b[0m", message);
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+
+    /// Prints the `//DIRED//` trailer Emacs reads the recorded name positions from.
+    pub fn finish(&mut self, quoting_style: &str) {
+        if let Some(dired) = self.dired.take() {
+            for (label, positions) in [("//DIRED//", dired), ("//SUBDIRED//", std::mem::take(&mut self.subdired))] {
+                if positions.is_empty() {
+                    continue;
+                }
+                self.write_str(label);
+                for (start, end) in positions {
+                    write!(self, " {} {}", start, end);
+                }
+                self.write_str("\n");
+            }
+            writeln!(self, "//DIRED-OPTIONS// --quoting-style={}", quoting_style);
+        }
+        self.flush();
+    }
+}
@@ -0,0 +1,64 @@
+use common::pool::Pool;
+
+use std::{
+    collections::HashMap,
+    path::{
+        Path,
+        PathBuf
+    },
+    sync::{
+        Condvar,
+        Mutex
+    }
+};
+
+/// A parallel directory walker.
+///
+/// Workers take directories from a shared pool, which hands out the most recently found first, so
+/// the tree is walked roughly depth first. Results are kept until `take()` claims them, so the caller
+/// can print in a deterministic order while the workers run ahead.
+pub struct Walker<T> {
+    pool: Pool<PathBuf>,
+    results: Mutex<HashMap<PathBuf, T>>,
+    result_available: Condvar
+}
+
+impl<T> Walker<T> {
+    pub fn new(root: PathBuf) -> Self {
+        let pool = Pool::new();
+        pool.push(root);
+
+        Walker {
+            pool,
+            results: Mutex::new(HashMap::new()),
+            result_available: Condvar::new()
+        }
+    }
+
+    /// Runs a worker until every directory has been scanned.
+    ///
+    /// `scan` returns the result for a directory along with the subdirectories to walk next.
+    pub fn run(&self, scan: impl Fn(&Path) -> (T, Vec<PathBuf>)) {
+        self.pool.work(|directory| {
+            let (result, subdirectories) = scan(&directory);
+            // reversed so the first subdirectory is the next one taken
+            self.pool.extend(subdirectories.into_iter().rev());
+
+            self.results.lock().unwrap().insert(directory, result);
+            self.result_available.notify_all();
+        });
+    }
+
+    /// Waits for the result of `directory` and removes it from the walker.
+    ///
+    /// `directory` must be the root or one of the subdirectories returned by a `scan`.
+    pub fn take(&self, directory: &Path) -> T {
+        let mut results = self.results.lock().unwrap();
+        loop {
+            if let Some(result) = results.remove(directory) {
+                return result;
+            }
+            results = self.result_available.wait(results).unwrap();
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime}
+};
+
+#[cfg(target_os = "linux")]
+use std::{
+    ffi::CString,
+    fs::File,
+    io::Read,
+    os::{
+        fd::FromRawFd,
+        unix::ffi::OsStrExt
+    }
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to wait for a burst of changes (e.g. a build) to settle before redrawing.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// The modification time and size of every entry below the watched directories.
+#[derive(PartialEq)]
+pub struct Snapshot {
+    entries: HashMap<PathBuf, (Option<SystemTime>, u64)>,
+    directories: Vec<PathBuf>,
+    /// whether hidden entries are included
+    all: bool
+}
+
+impl Snapshot {
+    /// Records the entries of `roots`, and of every subdirectory below them when `recursive`. Hidden
+    /// entries are left out unless `all`, as `ls` leaves them out of the listing.
+    /// Paths are built the same way `ls` builds them, so they can be compared with its entries.
+    pub fn take(roots: &[PathBuf], recursive: bool, all: bool) -> Self {
+        let mut snapshot = Snapshot {
+            entries: HashMap::new(),
+            directories: Vec::new(),
+            all
+        };
+
+        let mut to_visit: Vec<PathBuf> = roots.to_vec();
+        while let Some(directory) = to_visit.pop() {
+            let dir_entries = match fs::read_dir(&directory) {
+                Ok(entries) => entries,
+                Err(_) => continue
+            };
+            snapshot.directories.push(directory);
+
+            for entry in dir_entries.filter_map(|entry| entry.ok()) {
+                if !all && entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue
+                };
+                let path = entry.path();
+                if recursive && metadata.is_dir() {
+                    to_visit.push(path.clone());
+                }
+                snapshot.entries.insert(path, (metadata.modified().ok(), metadata.len()));
+            }
+        }
+
+        snapshot
+    }
+
+    /// The entries which are new or have changed since `previous`.
+    pub fn changed_since(&self, previous: &Snapshot) -> Vec<PathBuf> {
+        self.entries.iter()
+            .filter(|(path, state)| previous.entries.get(*path) != Some(*state))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+/// Waits for a change inside the directories of a snapshot.
+pub enum Watcher {
+    #[cfg(target_os = "linux")]
+    Inotify {
+        inotify: File,
+        /// whether changes to hidden entries count
+        all: bool
+    },
+    Poll
+}
+
+/// Whether every event read from inotify is about a hidden entry.
+#[cfg(target_os = "linux")]
+fn hidden_only(mut events: &[u8]) -> bool {
+    // struct inotify_event: wd, mask, cookie and len, then a NUL padded name of len bytes
+    const HEADER: usize = 16;
+    while events.len() >= HEADER {
+        let len = u32::from_ne_bytes([events[12], events[13], events[14], events[15]]) as usize;
+        let name = events.get(HEADER..HEADER + len).unwrap_or_default();
+        // events about a watched directory itself have no name
+        if name.first() != Some(&b'.') {
+            return false;
+        }
+        events = events.get(HEADER + len..).unwrap_or_default();
+    }
+    true
+}
+
+impl Watcher {
+    /// Starts watching every directory in `snapshot`, using inotify where possible.
+    pub fn new(snapshot: &Snapshot) -> Self {
+        #[cfg(target_os = "linux")] {
+            unsafe extern "C" {
+                fn inotify_init1(flags: i32) -> i32;
+                fn inotify_add_watch(fd: i32, path: *const u8, mask: u32) -> i32;
+            }
+
+            const IN_CLOEXEC: i32 = 0o2000000;
+            const IN_MODIFY: u32 = 0x002;
+            const IN_ATTRIB: u32 = 0x004;
+            const IN_MOVED_FROM: u32 = 0x040;
+            const IN_MOVED_TO: u32 = 0x080;
+            const IN_CREATE: u32 = 0x100;
+            const IN_DELETE: u32 = 0x200;
+            const IN_DELETE_SELF: u32 = 0x400;
+            const IN_MOVE_SELF: u32 = 0x800;
+            const MASK: u32 = IN_MODIFY | IN_ATTRIB | IN_MOVED_FROM | IN_MOVED_TO
+                | IN_CREATE | IN_DELETE | IN_DELETE_SELF | IN_MOVE_SELF;
+
+            // SAFETY: no pointers are involved
+            let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+            if fd >= 0 {
+                // SAFETY: the descriptor was just created and nothing else owns it
+                let inotify = unsafe { File::from_raw_fd(fd) };
+                let all_watched = snapshot.directories.iter().all(|directory| {
+                    let Ok(path) = CString::new(directory.as_os_str().as_bytes()) else {
+                        return false;
+                    };
+                    // SAFETY: the path is a valid C string
+                    unsafe { inotify_add_watch(fd, path.as_ptr().cast(), MASK) >= 0 }
+                });
+                // e.g. running out of watches on a huge tree
+                if all_watched {
+                    return Watcher::Inotify { inotify, all: snapshot.all };
+                }
+            }
+        }
+
+        Watcher::Poll
+    }
+
+    /// Blocks until something has changed. `changed` is used to compare snapshots when polling.
+    pub fn wait(self, changed: impl Fn() -> bool) {
+        match self {
+            #[cfg(target_os = "linux")]
+            Watcher::Inotify { mut inotify, all } => {
+                let mut events = [0u8; 4096];
+                loop {
+                    let Ok(read) = inotify.read(&mut events) else {
+                        return Watcher::Poll.wait(changed);
+                    };
+                    // hidden entries aren't listed, so there's nothing to redraw
+                    if all || !hidden_only(&events[..read]) {
+                        break;
+                    }
+                }
+            },
+            Watcher::Poll => {
+                while !changed() {
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+        thread::sleep(SETTLE_TIME);
+    }
+}
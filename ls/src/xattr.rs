@@ -0,0 +1,34 @@
+use common::xattr;
+
+use std::{ffi::OsStr, path::Path};
+
+/// Lists the extended attributes of `path` (without following symlinks) as (name, size) pairs.
+pub fn list(path: &Path) -> Vec<(String, usize)> {
+    xattr::list(path).unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| {
+            let size = xattr::size(path, &name).ok()?;
+            Some((name.to_string_lossy().to_string(), size))
+        })
+        .collect()
+}
+
+/// The SELinux security context of `path`, if it has one.
+pub fn security_context(path: &Path) -> Option<String> {
+    let context = xattr::get(path, OsStr::new("security.selinux")).ok()?;
+    let context = String::from_utf8_lossy(&context);
+    Some(context.trim_end_matches('\0').to_string())
+}
+
+/// The character GNU `ls -l` appends to the mode string: `+` for an ACL and `.` for a security context
+/// without one. Other extended attributes aren't marked, `--xattr` lists those.
+pub fn mode_marker(xattrs: &[(String, usize)]) -> Option<char> {
+    let names = || xattrs.iter().map(|(name, _)| name.as_str());
+    if names().any(|name| name == "system.posix_acl_access" || name == "system.posix_acl_default") {
+        Some('+')
+    } else if names().any(|name| name == "security.selinux") {
+        Some('.')
+    } else {
+        None
+    }
+}
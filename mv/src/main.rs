@@ -1,33 +1,116 @@
+use common::{
+    backup::{backup, backup_path, BackupControl},
+    copy,
+    help::{Opt, Tool},
+    journal::{self, Kind},
+    protect::Protection,
+    size
+};
+
 use std::{
     env,
-    fs,
+    fs::{self, Metadata},
     io::{
         ErrorKind,
         Write,
         stdin,
         stdout
     },
-    path::PathBuf
+    path::{Path, PathBuf}
 };
 
-#[derive(PartialEq)]
-enum MVBackup {
-    None,
-    Numbered,
-    Existing,
-    Simple
-}
+const MV: Tool = Tool {
+    name: "mv",
+    version: env!("CARGO_PKG_VERSION"),
+    usage: "[OPTION]... [-T] SOURCE DEST
+[OPTION]... SOURCE... DIRECTORY
+[OPTION]... -t DIRECTORY SOURCE...",
+    description: "Rename SOURCE to DEST, or move SOURCE(s) to DIRECTORY.",
+    options: &[
+        Opt::new(&['b'], "backup", "make a backup of each existing destination file; CONTROL is none (off), \
+            numbered (t), existing (nil) or simple (never)").value("[=CONTROL]"),
+        Opt::new(&[], "debug", "explain how a file is copied; implies --verbose"),
+        Opt::new(&['f'], "force", "do not prompt before overwriting"),
+        Opt::new(&['i'], "interactive", "prompt before overwrite"),
+        Opt::new(&['n'], "no-clobber", "do not overwrite an existing file"),
+        Opt::new(&[], "strip-trailing-slashes", "remove any trailing slashes from each SOURCE argument"),
+        Opt::new(&['t'], "target-directory", "move all SOURCE arguments into DIRECTORY").value("=DIRECTORY"),
+        Opt::new(&['T'], "no-target-directory", "treat DEST as a normal file; an empty directory DEST is \
+            replaced in one step"),
+        Opt::new(&[], "verbose", "explain what is being done"),
+        Opt::new(&[], "dry-run", "print what would be moved, and how much, without moving anything"),
+        Opt::new(&[], "force-protected", "overwrite protected files too (see below)")
+    ],
+    notes: "The backup suffix is '~'.  The version control method may be selected via the
+--backup option or through the VERSION_CONTROL environment variable.
+
+Files matching a pattern in ~/.config/cli-tools/protect, or in a .rmprotect
+file in any directory above them, are protected: mv refuses to overwrite them
+unless --force-protected is given.
+
+A move to another file system copies everything and then removes the original,
+keeping times, permissions, owner and group (where permitted), extended
+attributes and ACLs; --debug lists anything that couldn't be kept.
+
+Moves are recorded so that they can be reversed with `undo`."
+};
 
 struct MVOpts {
     backup: bool,
-    backup_control: MVBackup,
+    backup_control: BackupControl,
     backup_suffix: String,
     debug: bool,
     force: bool,
     interactive: bool,
     no_clobber: bool,
     strip_trailing_slashes: bool,
-    verbose: bool
+    verbose: bool,
+    dry_run: bool,
+    /// `-t`: every operand is moved into this directory
+    target_directory: Option<PathBuf>,
+    /// `-T`: the destination is replaced, even if it's a directory
+    no_target_directory: bool,
+    /// `None` with `--force-protected`
+    protection: Option<Protection>
+}
+
+/// What `--dry-run` found would be moved.
+#[derive(Default)]
+struct Plan {
+    files: u64,
+    directories: u64,
+    bytes: u64
+}
+
+impl Plan {
+    /// Counts `path` and everything below it.
+    fn add(&mut self, path: &Path) {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return;
+        };
+        if !metadata.is_dir() {
+            self.files += 1;
+            self.bytes += metadata.len();
+            return;
+        }
+
+        self.directories += 1;
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                self.add(&entry.path());
+            }
+        }
+    }
+
+    fn print(&self) {
+        println!("mv: would move {} file{} and {} director{} ({} bytes, {})",
+            self.files,
+            if self.files == 1 { "" } else { "s" },
+            self.directories,
+            if self.directories == 1 { "y" } else { "ies" },
+            self.bytes,
+            size::format(self.bytes));
+    }
 }
 
 fn input(prompt: &str) -> Option<String> {
@@ -47,155 +130,251 @@ fn prompt(prompt: &str) -> bool {
         None => return false
     };
 
-    return matches!(user_input.to_lowercase().as_str(), "y" | "ye" | "yes");
+    matches!(user_input.to_lowercase().as_str(), "y" | "ye" | "yes")
+}
+
+#[cfg(unix)]
+fn device(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.dev()
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &Metadata) -> u64 {
+    0
 }
 
-fn backup(path: &PathBuf, options: &MVOpts) -> Option<String> {
-    if path.is_dir() {
-        return None;
+/// Whether `rename()` would fail with `CrossesDevices`, so the move falls back to copying.
+fn crosses_devices(source: &Path, destination: &Path) -> bool {
+    let parent = match destination.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    };
+    match (fs::symlink_metadata(source), fs::metadata(parent)) {
+        (Ok(source), Ok(parent)) => device(&source) != device(&parent),
+        _ => false
     }
+}
 
-    let original_file = match path.file_name() {
-        Some(name) => name.to_string_lossy(),
-        None => return None
+/// Whether `destination` is protected from being overwritten (see `common::protect`). Reports it if so.
+fn protected(options: &MVOpts, destination: &Path) -> bool {
+    let Some(reason) = options.protection.as_ref().and_then(|protection| protection.reason(destination)) else {
+        return false;
     };
-    
-    let parent = match path.parent() {
-        Some(parent) => parent,
-        None => return None
+    println!("\x1b[0;91mError: Refusing to overwrite '{}', it is protected by {} (use --force-protected to override).\x1b[0m",
+        destination.to_string_lossy(), reason);
+    true
+}
+
+/// Whether `destination` is a directory and `source` isn't, or the other way around, in which case
+/// `destination` can't be replaced by `source`. Reports it if so.
+fn mismatched(source: &Path, destination: &Path) -> bool {
+    let (Ok(source_metadata), Ok(destination_metadata)) = (fs::symlink_metadata(source), fs::symlink_metadata(destination)) else {
+        return false;
     };
+    match (source_metadata.is_dir(), destination_metadata.is_dir()) {
+        (false, true) => println!("\x1b[0;91mError: Cannot overwrite directory '{}' with non-directory.\x1b[0m",
+            destination.to_string_lossy()),
+        (true, false) => println!("\x1b[0;91mError: Cannot overwrite non-directory '{}' with directory '{}'.\x1b[0m",
+            destination.to_string_lossy(), source.to_string_lossy()),
+        _ => return false
+    }
+    true
+}
 
-    match options.backup_control {
-        MVBackup::None => return None,
-        MVBackup::Simple => {
-            let new_name = format!("{}{}", original_file, options.backup_suffix);
-            let mut backup_file = parent.to_path_buf();
-            backup_file.push(&new_name);
-            match fs::rename(path, &backup_file) {
-                Ok(_) => return Some(new_name),
-                Err(_) => match fs::copy(path, &backup_file) {
-                    Ok(_) => return Some(new_name),
-                    Err(_) => return None
-                }
-            }
+/// Records a move in the journal, so `undo` can reverse it.
+fn record(kind: Kind, from: &Path, to: &Path) {
+    if let Err(err) = journal::record("mv", kind, from, to) {
+        println!("\x1b[0;91mWarning: Could not record '{}' in the journal: {}.\x1b[0m",
+            from.to_string_lossy(), err);
+    }
+}
+
+/// `--dry-run`: reports what moving `source` to `destination` would do, and counts it.
+fn plan(options: &MVOpts, source: &Path, destination: &Path, totals: &mut Plan) {
+    if fs::symlink_metadata(destination).is_ok() {
+        if protected(options, destination) || mismatched(source, destination) {
+            return;
+        }
+        if options.no_clobber {
+            println!("would skip '{}', '{}' exists", source.to_string_lossy(), destination.to_string_lossy());
+            return;
+        }
+        match backup_path(destination, options.backup_control, &options.backup_suffix) {
+            Some(backup_file) if options.backup =>
+                println!("would back up '{}' -> '{}'",
+                    destination.to_string_lossy(),
+                    backup_file.to_string_lossy()),
+            _ => println!("would overwrite '{}'", destination.to_string_lossy())
         }
-        MVBackup::Existing | MVBackup::Numbered => {
-            let files = match fs::read_dir(parent) {
-                Ok(entries) => entries,
-                Err(_) => return None
-            };
-
-            let numbered_prefix = format!("{}.~", original_file);
-            let numbered_suffix = "~".to_string();
-
-            let simple_name = format!("{}{}", original_file, options.backup_suffix);
-
-            let mut latest_backup: i64 = 0;
-            let mut found_simple: bool = false;
-            for entry in files {
-                match entry {
-                    Ok(entry) => {
-                        let entry_path = entry.path();
-                        let filename = match entry_path.file_name() {
-                            Some(name) => name.to_string_lossy(),
-                            None => continue
-                        };
-                        match filename.strip_prefix(&numbered_prefix) {
-                            Some(filename) => {
-                                match filename.strip_suffix(&numbered_suffix) {
-                                    Some(filename) => {
-                                        if filename.is_empty() {
-                                            continue;
-                                        }
-                                        let n: i64 = match filename.parse() {
-                                            Ok(n) => n,
-                                            Err(_) => continue
-                                        };
-                                        
-                                        if n > latest_backup {
-                                            latest_backup = n;
-                                        }
-                                    },
-                                    None => continue
-                                }
-                            },
-                            None => {
-                                if filename == simple_name {
-                                    found_simple = true;
-                                }
-                            }
-                        }
-                    },
-                    Err(_) => continue
+    }
+
+    if crosses_devices(source, destination) {
+        println!("would copy '{}' -> '{}' and remove '{}' (different devices)",
+            source.to_string_lossy(),
+            destination.to_string_lossy(),
+            source.to_string_lossy());
+    } else {
+        println!("would rename '{}' -> '{}'",
+            source.to_string_lossy(),
+            destination.to_string_lossy());
+    }
+    totals.add(source);
+}
+
+/// Moves `source` to another file system by copying it, then removing the original once the copy is
+/// complete. If the copy fails, nothing is left of it and the original is kept. Anything about the
+/// original which couldn't be kept (e.g. its owner) is listed with `--debug`.
+fn move_across(options: &MVOpts, source: &Path, destination: &Path) {
+    match copy::copy(source, destination) {
+        Ok(unpreserved) => {
+            record(Kind::Move, source, destination);
+            if options.debug {
+                println!("copied '{}' -> '{}'",
+                    source.to_string_lossy(),
+                    destination.to_string_lossy());
+                for lost in unpreserved {
+                    println!("could not preserve {} of '{}': {}",
+                        lost.what,
+                        lost.path.to_string_lossy(),
+                        lost.err);
                 }
+            } else if options.verbose {
+                println!("renamed '{}' -> '{}'",
+                    source.to_string_lossy(),
+                    destination.to_string_lossy());
+            }
+            match copy::remove(source) {
+                Ok(_) => {
+                    if options.debug {
+                        println!("removed '{}'", source.to_string_lossy());
+                    }
+                },
+                Err(err) => println!("\x1b[0;91mError: Failed to remove original '{}' after copying: {}.\x1b[0m",
+                    source.to_string_lossy(), err)
             }
+        },
+        Err(err) => println!("\x1b[0;91mError: Failed to copy '{}' to '{}': {}.\x1b[0m",
+            source.to_string_lossy(), destination.to_string_lossy(), err)
+    }
+}
 
-            let mut backup_file = parent.to_path_buf();
-            if options.backup_control == MVBackup::Numbered || latest_backup != 0 || found_simple {
-                backup_file.push(format!("{}.~{}~", original_file, latest_backup + 1));
-            } else {
-                backup_file.push(format!("{}{}", original_file, options.backup_suffix));
+/// Renames `source` to `destination`, copying it if they're on different file systems.
+fn move_path(options: &MVOpts, source: &Path, destination: &Path) {
+    match fs::rename(source, destination) {
+        Ok(_) => {
+            record(Kind::Move, source, destination);
+            if options.verbose {
+                println!("renamed '{}' -> '{}'",
+                    source.to_string_lossy(),
+                    destination.to_string_lossy());
             }
+        },
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => move_across(options, source, destination),
+        Err(err) => println!("\x1b[0;91mError: Failed to rename '{}' to '{}': {}.\x1b[0m",
+            source.to_string_lossy(), destination.to_string_lossy(), err)
+    }
+}
 
-            let new_name = match backup_file.file_name() {
-                Some(name) => name.to_string_lossy().to_string(),
-                None => return None
-            };
+/// Decides what happens to an existing `destination` before `source` replaces it: nothing if it's
+/// protected or of the other kind (directory or not), `-n` keeps it, `-i` asks and `-b` backs it up.
+/// Returns whether the move can go ahead.
+fn make_way(options: &MVOpts, source: &Path, destination: &Path) -> bool {
+    if fs::symlink_metadata(destination).is_err() {
+        return true;
+    }
+    if protected(options, destination) || mismatched(source, destination) || options.no_clobber {
+        return false;
+    }
+    if !options.force && options.interactive
+        && !prompt(format!("overwrite '{}'", destination.to_string_lossy()).as_str()) {
+        return false;
+    }
 
-            match fs::rename(path, &backup_file) {
-                Ok(_) => return Some(new_name),
-                Err(_) => match fs::copy(path, &backup_file) {
-                    Ok(_) => return Some(new_name),
-                    Err(_) => return None
-                }
-            }
+    if options.backup
+        && let Some(backup_file) = backup(destination, options.backup_control, &options.backup_suffix) {
+        record(Kind::Backup, destination, &destination.with_file_name(&backup_file));
+        if options.verbose {
+            println!("backup '{}' -> '{}'",
+                destination.to_string_lossy(),
+                backup_file);
         }
-    };
+    }
+    true
 }
 
-fn mv(options: &MVOpts, paths: &Vec<PathBuf>) {
-    if paths.len() < 2 {
-        println!("\x1b[0;91mError: Not enough paths supplied, expected at least 2 paths.\x1b[0m");
+/// Moves `source` to exactly `destination`, rather than into it.
+fn rename(options: &MVOpts, source: &Path, destination: &Path) {
+    if options.dry_run {
+        let mut dry_run = Plan::default();
+        plan(options, source, destination, &mut dry_run);
+        dry_run.print();
         return;
     }
+    if make_way(options, source, destination) {
+        move_path(options, source, destination);
+    }
+}
 
+fn mv(options: &MVOpts, paths: &[PathBuf]) {
     let paths: Vec<PathBuf> = if options.strip_trailing_slashes {
         paths
             .iter()
             .map(|path| path.components().collect())
             .collect()
     } else {
-        paths.clone()
+        paths.to_vec()
     };
 
-    let target = paths.last().unwrap();
-
-    if !target.is_dir() {
-        if paths.len() > 2 {
-            println!("\x1b[0;91mError: Too many paths supplied for rename operation.\x1b[0m");
-            return;
-        }
-        match fs::rename(&paths[0], &paths[1]) {
-            Ok(_) => {
-                if options.verbose {
-                    println!("renamed '{}' -> '{}'",
-                        paths[0].to_string_lossy(),
-                        paths[1].to_string_lossy());
-                }
-            },
-            Err(_) =>
-                println!("\x1b[0;91mError: Failed to rename '{}' to '{}'.\x1b[0m",
-                    paths[0].to_string_lossy(),
-                    paths[1].to_string_lossy())
+    if options.no_target_directory {
+        match paths.len() {
+            0 => println!("\x1b[0;91mError: No paths supplied.\x1b[0m"),
+            1 => println!("\x1b[0;91mError: Missing destination after '{}'.\x1b[0m", paths[0].to_string_lossy()),
+            2 => rename(options, &paths[0], &paths[1]),
+            _ => println!("\x1b[0;91mError: Extra operand '{}'.\x1b[0m", paths[2].to_string_lossy())
         }
         return;
     }
-    
-    let mut crosses_devices = false;
-    for path in paths.iter().take(
-        paths.len()
-            .saturating_sub(1)
-    ) {
+
+    let (sources, target): (&[PathBuf], &Path) = match &options.target_directory {
+        Some(target) => {
+            match fs::metadata(target) {
+                Ok(metadata) if metadata.is_dir() => {},
+                Ok(_) => {
+                    println!("\x1b[0;91mError: Target '{}' is not a directory.\x1b[0m", target.to_string_lossy());
+                    return;
+                },
+                Err(err) => {
+                    println!("\x1b[0;91mError: Target directory '{}': {}.\x1b[0m", target.to_string_lossy(), err);
+                    return;
+                }
+            }
+            if paths.is_empty() {
+                println!("\x1b[0;91mError: No paths supplied.\x1b[0m");
+                return;
+            }
+            (&paths, target)
+        },
+        None => {
+            if paths.len() < 2 {
+                println!("\x1b[0;91mError: Not enough paths supplied, expected at least 2 paths.\x1b[0m");
+                return;
+            }
+            let (target, sources) = paths.split_last().unwrap();
+            if !target.is_dir() {
+                if paths.len() > 2 {
+                    println!("\x1b[0;91mError: Too many paths supplied for rename operation.\x1b[0m");
+                    return;
+                }
+                rename(options, &paths[0], &paths[1]);
+                return;
+            }
+            (sources, target)
+        }
+    };
+
+    let mut dry_run = Plan::default();
+    for path in sources {
         if !path.exists() {
             println!("\x1b[0;91mError: Path not found '{}'.\x1b[0m", path.to_string_lossy());
             continue;
@@ -209,102 +388,64 @@ fn mv(options: &MVOpts, paths: &Vec<PathBuf>) {
             }
         };
         let destination_path: &PathBuf = &target.join(file_name);
-        if destination_path.exists() {
-            if !options.force && options.interactive {
-                if !prompt(format!("overwrite '{}'", destination_path.to_string_lossy()).as_str()) {
-                    continue;
-                }
-            } else if options.no_clobber {
-                continue;
-            }
-
-            if options.backup {
-                if let Some(backup_file) = backup(destination_path, options) {
-                    if options.verbose {
-                        println!("backup '{}' -> '{}'",
-                            destination_path.to_string_lossy(),
-                            backup_file);
-                    }
-                }
-            }
+        if options.dry_run {
+            plan(options, path, destination_path, &mut dry_run);
+            continue;
         }
-
-        if !crosses_devices {
-            match fs::rename(path, destination_path) {
-                Ok(_) => {
-                    if options.verbose {
-                        println!("renamed '{}' -> '{}'",
-                            path.to_string_lossy(),
-                            destination_path.to_string_lossy());
-                    }
-                    continue;
-                },
-                Err(err) => match err.kind() {
-                    ErrorKind::CrossesDevices => crosses_devices = true,
-                    _ => {}
-                }
-            }
+        if make_way(options, path, destination_path) {
+            move_path(options, path, destination_path);
         }
+    }
 
-        match fs::copy(path, destination_path) {
-            Ok(_) => {
-                if options.debug {
-                    println!("copied '{}' -> '{}'",
-                        path.to_string_lossy(),
-                        destination_path.to_string_lossy());
-                } else if options.verbose {
-                    println!("renamed '{}' -> '{}'",
-                        path.to_string_lossy(),
-                        destination_path.to_string_lossy());
-                }
-                match fs::remove_file(path) {
-                    Ok(_) => {
-                        if options.debug {
-                            println!("removed '{}'", path.to_string_lossy());
-                        }
-                    },
-                    Err(_) => println!("\x1b[0;91mError: Failed to remove original file '{}' after copying.\x1b[0m", path.to_string_lossy())
-                }
-            },
-            Err(_) => println!("\x1b[0;91mError: Failed to copy '{}' to '{}'.\x1b[0m", path.to_string_lossy(), destination_path.to_string_lossy())
-        }
+    if options.dry_run {
+        dry_run.print();
     }
 }
 
 fn main() {
     let mut options: MVOpts = MVOpts {
         backup: false,
-        backup_control: MVBackup::Existing,
+        backup_control: BackupControl::Existing,
         backup_suffix: "~".to_string(),
         debug: false,
         force: false,
         interactive: true,
         no_clobber: false,
         strip_trailing_slashes: false,
-        verbose: false
+        verbose: false,
+        dry_run: false,
+        target_directory: None,
+        no_target_directory: false,
+        protection: None
     };
 
     let mut paths: Vec<PathBuf> = Vec::new();
+    let mut force_protected = false;
 
     let args: Vec<String> = env::args().collect();
     let mut expanded_args: Vec<String> = Vec::new();
 
-    match env::var("VERSION_CONTROL") {
-        Ok(version) => expanded_args.push(format!("--backup={}", version)),
-        Err(_) => {}
+    if let Ok(version) = env::var("VERSION_CONTROL") {
+        expanded_args.push(format!("--backup={}", version));
     }
 
     for arg in args.iter().skip(1) {
         if arg.len() > 2 && arg.starts_with('-') && !arg.starts_with("--") {
-            for ch in arg.chars().skip(1) {
+            for (i, ch) in arg.char_indices().skip(1) {
                 expanded_args.push(format!("-{}", ch));
+                // like getopt, `-tDIR` takes the rest of the argument as its value
+                if ch == 't' && i + 1 < arg.len() {
+                    expanded_args.push(arg[i + 1..].to_string());
+                    break;
+                }
             }
         } else {
             expanded_args.push(arg.to_string());
         }
     }
 
-    for arg in expanded_args {
+    let mut expanded_args = expanded_args.into_iter();
+    while let Some(arg) = expanded_args.next() {
         match arg.as_str() {
             "-b" | "--backup" => options.backup = true,
             "--debug" => {
@@ -316,18 +457,35 @@ fn main() {
             "-n" | "--no-clobber" => options.no_clobber = true,
             "--strip-trailing-slashes" => options.strip_trailing_slashes = true,
             "--verbose" => options.verbose = true,
+            "--dry-run" => options.dry_run = true,
+            "--force-protected" => force_protected = true,
+            "-t" | "--target-directory" => match expanded_args.next() {
+                Some(directory) => options.target_directory = Some(PathBuf::from(directory)),
+                None => {
+                    println!("\x1b[0;91mError: Option '{}' requires a directory.\x1b[0m", arg);
+                    return;
+                }
+            },
+            arg if arg.starts_with("--target-directory=") =>
+                options.target_directory = Some(PathBuf::from(&arg["--target-directory=".len()..])),
+            "-T" | "--no-target-directory" => options.no_target_directory = true,
+            "--help" => {
+                print!("{}", MV.help());
+                return;
+            },
+            "--version" => {
+                print!("{}", MV.version());
+                return;
+            },
             arg if arg.starts_with("--backup=") => {
                 let version = match arg.strip_prefix("--backup=") {
                     Some(version) => version.to_lowercase(),
                     None => continue
                 };
                 options.backup = true;
-                match version.as_str() {
-                    "t" | "numbered" => options.backup_control = MVBackup::Numbered,
-                    "nil" | "existing" => options.backup_control = MVBackup::Existing,
-                    "never" | "simple" => options.backup_control = MVBackup::Simple,
-                    "none" | "off" => options.backup_control = MVBackup::None,
-                    _ => {
+                match BackupControl::parse(&version) {
+                    Some(control) => options.backup_control = control,
+                    None => {
                         println!("\x1b[0;91mError: Unknown version control '{}'.\x1b[0m", version);
                         return;
                     }
@@ -342,5 +500,14 @@ fn main() {
         }
     }
 
+    if options.target_directory.is_some() && options.no_target_directory {
+        println!("\x1b[0;91mError: Options '--target-directory' (-t) and '--no-target-directory' (-T) are incompatible.\x1b[0m");
+        return;
+    }
+
+    if !force_protected {
+        options.protection = Some(Protection::load());
+    }
+
     mv(&options, &paths);
 }
@@ -1,7 +1,23 @@
+use common::{
+    config,
+    help::{Opt, Tool},
+    glob,
+    journal::{self, Kind},
+    protect::Protection,
+    size,
+    time,
+    trash
+};
+
 use std::{
     env,
-    fs,
+    fs::{
+        self,
+        Metadata
+    },
     io::{
+        self,
+        IsTerminal,
         Result,
         stdin,
         stdout,
@@ -10,124 +26,656 @@ use std::{
     path::{
         Path,
         PathBuf
-    }
+    },
+    process,
+    sync::{
+        Mutex,
+        atomic::{
+            AtomicBool,
+            AtomicU64,
+            Ordering
+        }
+    },
+    thread,
+    time::SystemTime
+};
+
+mod parallel;
+mod shred;
+
+const RM: Tool = Tool {
+    name: "rm",
+    version: env!("CARGO_PKG_VERSION"),
+    usage: "[OPTION]... [FILE]...",
+    description: "Remove (unlink) the FILE(s).",
+    options: &[
+        Opt::new(&['f'], "force", "ignore nonexistent files and arguments, never prompt"),
+        Opt::new(&['i'], "", "prompt before every removal"),
+        Opt::new(&['I'], "", "prompt once before removing more than three files, or when removing recursively; \
+            less intrusive than -i, while still giving protection against most mistakes"),
+        Opt::new(&[], "interactive", "prompt according to WHEN: never, once (-I), or always (-i); \
+            without WHEN, prompt always").value("[=WHEN]"),
+        Opt::new(&[], "one-file-system", "when removing a hierarchy recursively, skip any directory that is \
+            on a file system different from that of the corresponding command line argument"),
+        Opt::new(&[], "no-preserve-root", "do not treat '/' specially"),
+        Opt::new(&[], "preserve-root", "do not remove '/' (default); with 'all', reject any command line \
+            argument on a separate device from its parent").value("[=all]"),
+        Opt::new(&['r', 'R'], "recursive", "remove directories and their contents recursively"),
+        Opt::new(&[], "exclude", "leave files and directories matching GLOB alone; a GLOB without a '/' is \
+            matched against file names, otherwise against the end of the path (see below)").value("=GLOB"),
+        Opt::new(&[], "older-than", "only remove files last modified more than AGE ago, e.g. 90s, 30m, 12h, \
+            7d or 2w (days without a unit)").value("=AGE"),
+        Opt::new(&[], "larger-than", "only remove files larger than SIZE, e.g. 512K, 10M or 1G (powers of \
+            1024, bytes without a unit)").value("=SIZE"),
+        Opt::new(&[], "force-protected", "remove protected files too (see below)"),
+        Opt::new(&[], "jobs", "remove with N threads when removing recursively (default: one per CPU); the \
+            removal is done in order with a single thread when every file is prompted for (-i)").value(" N"),
+        Opt::new(&['d'], "dir", "remove empty directories"),
+        Opt::new(&['v'], "verbose", "explain what is being done"),
+        Opt::new(&[], "trash", "move files to the trash instead of removing them"),
+        Opt::new(&[], "no-trash", "remove files, even if --trash is set in rm.conf"),
+        Opt::new(&[], "dry-run", "print what would be removed, and how much, without removing anything; \
+            nothing is prompted for"),
+        Opt::new(&[], "shred", "overwrite regular files N times (default 3) with random data and then with \
+            zeros, and rename them to obscure their names, before removing them; with -r, this applies to \
+            every file in the hierarchy").value("[=N]")
+    ],
+    notes: "Default options can be listed one per line in ~/.config/cli-tools/rm.conf,
+e.g. `--trash` to always move files to the trash.
+
+With --exclude, --older-than or --larger-than, a directory is only removed
+if it ends up empty; anything kept is listed with --verbose.  GLOB may use
+'*' and '?' (which don't match '/'), '**' (which does) and [...] classes.
+
+Files matching a pattern in ~/.config/cli-tools/protect, or in a .rmprotect
+file in any directory above them, are protected: rm refuses to remove them, or
+anything inside them, unless --force-protected is given.  Both files hold one
+GLOB per line, e.g. `.git` or `.env`; in a .rmprotect, a GLOB with a '/' is
+relative to the directory the .rmprotect is in.
+
+By default, rm does not remove directories.  Use the --recursive (-r or -R)
+option to remove each listed directory, too, along with all of its contents.
+
+To remove a file whose name starts with a '-', for example '-foo',
+use this command:
+  rm ./-foo
+
+Note that if you use rm to remove a file, it might be possible to recover
+some of its contents, given sufficient expertise and/or time.  For greater
+assurance that the contents are truly unrecoverable, consider using --shred.
+
+CAUTION: --shred relies on the file system overwriting data in place.  On
+copy-on-write or log-structured file systems (e.g. btrfs, ZFS, NILFS), on
+compressed or snapshotted file systems, and on SSDs with wear levelling, old
+copies of the data may survive, so --shred gives no real guarantee there."
 };
 
+/// Set whenever something couldn't be removed, for the exit status.
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// What `--dry-run` found would be removed.
+struct Plan {
+    files: AtomicU64,
+    directories: AtomicU64,
+    bytes: AtomicU64
+}
+
+static PLAN: Plan = Plan {
+    files: AtomicU64::new(0),
+    directories: AtomicU64::new(0),
+    bytes: AtomicU64::new(0)
+};
+
+/// Held while asking something, so the workers of a parallel removal take turns at the terminal.
+static PROMPTING: Mutex<()> = Mutex::new(());
+
 pub trait PathExt {
-    fn is_empty(&self) -> Result<bool>;
+    fn is_empty_dir(&self) -> Result<bool>;
 }
 
 impl PathExt for Path {
-    fn is_empty(&self) -> Result<bool> {
-        match fs::metadata(self) {
-            Ok(metadata) => {
-                if !metadata.is_dir() {
-                    return Ok(false);
-                }
-            },
-            Err(err) => return Err(err)
+    fn is_empty_dir(&self) -> Result<bool> {
+        if !fs::metadata(self)?.is_dir() {
+            return Ok(false);
         }
-        let mut files = match fs::read_dir(self) {
-            Ok(entries) => entries,
-            Err(err) => return Err(err)
-        };
-        return Ok(files.next().is_none());
+        let mut files = fs::read_dir(self)?;
+        Ok(files.next().is_none())
     }
 }
 
 #[derive(PartialEq)]
 enum RMPrompting {
     Never,
+    /// Only for write-protected files, when stdin is a terminal (the default).
+    Sometimes,
+    /// Like `Sometimes`, but also once up front (`-I`).
     Once,
     Always
 }
 
+#[derive(PartialEq)]
+enum RMPreserveRoot {
+    No,
+    Root,
+    All
+}
+
 struct RMOpts {
     force: bool,
     prompt: RMPrompting,
     recursive: bool,
+    preserve_root: RMPreserveRoot,
+    one_file_system: bool,
     directories: bool,
     verbose: bool,
-    version: bool
+    trash: bool,
+    /// `--shred[=N]`: the number of random passes to overwrite files with before unlinking them.
+    shred: Option<usize>,
+    dry_run: bool,
+    /// the number of threads `-r` removes with
+    jobs: usize,
+    /// `--exclude`: patterns of files and directories to leave alone
+    exclude: Vec<String>,
+    /// `--older-than`: only files last modified before this are removed
+    older_than: Option<SystemTime>,
+    /// `--larger-than`: only files larger than this many bytes are removed
+    larger_than: Option<u64>,
+    /// `None` with `--force-protected`
+    protection: Option<Protection>
 }
 
 fn input(prompt: &str) -> String {
     print!("{}", prompt);
-    match stdout().flush() {
-        Ok(_) => {},
-        Err(_) => {}
-    }
+    let _ = stdout().flush();
     let mut user_input: String = String::new();
-    match stdin().read_line(&mut user_input) {
-        Ok(_) => {},
-        Err(_) => {}
-    }
+    let _ = stdin().read_line(&mut user_input);
     // TODO: find better method than `.trim()` as this would strip intentional spaces in addition to the `\n` (not really an issue here, but unintuitive)
-    return user_input.trim().to_string();
+    user_input.trim().to_string()
 }
 
 fn prompt(prompt: &str) -> bool {
+    let _prompting = PROMPTING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
     let user_input: String = input(prompt);
 
-    return match user_input.to_lowercase().as_str() {
-        "y" | "ye" | "yes" => true,
-        _ => false
+    matches!(user_input.to_lowercase().as_str(), "y" | "ye" | "yes")
+}
+
+/// `--dry-run`: counts `path` towards the plan and reports what would be done with it.
+fn plan(path: &Path, metadata: &Metadata, action: &str) {
+    if metadata.is_dir() {
+        PLAN.directories.fetch_add(1, Ordering::Relaxed);
+    } else {
+        PLAN.files.fetch_add(1, Ordering::Relaxed);
+        PLAN.bytes.fetch_add(metadata.len(), Ordering::Relaxed);
+    }
+    if !action.is_empty() {
+        println!("would {} '{}'", action, path.to_string_lossy());
+    }
+}
+
+/// `--dry-run`: counts everything below `path` towards the plan, without reporting it.
+fn plan_tree(path: &Path) {
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let entry_path = entry.path();
+        let Ok(metadata) = fs::symlink_metadata(&entry_path) else {
+            continue;
+        };
+        plan(&entry_path, &metadata, "");
+        if metadata.is_dir() {
+            plan_tree(&entry_path);
+        }
+    }
+}
+
+fn print_plan() {
+    let files = PLAN.files.load(Ordering::Relaxed);
+    let directories = PLAN.directories.load(Ordering::Relaxed);
+    println!("rm: would remove {} file{} and {} director{} ({} bytes, {})",
+        files,
+        if files == 1 { "" } else { "s" },
+        directories,
+        if directories == 1 { "y" } else { "ies" },
+        PLAN.bytes.load(Ordering::Relaxed),
+        size::format(PLAN.bytes.load(Ordering::Relaxed)));
+}
+
+/// Moves `path` to the trash instead of removing it, returns whether that succeeded.
+fn move_to_trash(options: &RMOpts, path: &Path) -> bool {
+    if options.dry_run {
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            plan(path, &metadata, "trash");
+            if metadata.is_dir() {
+                plan_tree(path);
+            }
+        }
+        return true;
+    }
+
+    match trash::put(path) {
+        Ok(destination) => {
+            // so `undo` can bring it back
+            if let Err(err) = journal::record("rm", Kind::Trash, path, &destination) {
+                eprintln!("\x1b[0;91mrm: cannot record '{}' in the journal: {}\x1b[0m",
+                    path.to_string_lossy(), err);
+            }
+            if options.verbose {
+                println!("trashed '{}'", path.to_string_lossy());
+            }
+            true
+        },
+        Err(err) => {
+            FAILED.store(true, Ordering::Relaxed);
+            eprintln!("\x1b[0;91mrm: cannot move '{}' to the trash: {}\x1b[0m",
+                path.to_string_lossy(), err);
+            false
+        }
+    }
+}
+
+#[cfg(unix)]
+fn device(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.dev()
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &Metadata) -> u64 {
+    0
+}
+
+/// The `--preserve-root` failsafe, returns whether `path` may be removed recursively.
+fn check_preserve_root(options: &RMOpts, path: &Path) -> bool {
+    if options.preserve_root == RMPreserveRoot::No {
+        return true;
+    }
+
+    if fs::canonicalize(path).is_ok_and(|path| path.parent().is_none()) {
+        eprintln!("\x1b[0;91mrm: it is dangerous to operate recursively on '{}'\x1b[0m", path.to_string_lossy());
+        eprintln!("\x1b[0;91mrm: use --no-preserve-root to override this failsafe\x1b[0m");
+        FAILED.store(true, Ordering::Relaxed);
+        return false;
+    }
+
+    if options.preserve_root == RMPreserveRoot::All
+        && let (Ok(metadata), Ok(parent)) = (fs::symlink_metadata(path), fs::metadata(path.join("..")))
+        && device(&metadata) != device(&parent) {
+        eprintln!("\x1b[0;91mrm: skipping '{}', since it's on a different device\x1b[0m", path.to_string_lossy());
+        eprintln!("\x1b[0;91mrm: and --preserve-root=all is in effect\x1b[0m");
+        FAILED.store(true, Ordering::Relaxed);
+        return false;
+    }
+
+    true
+}
+
+/// An OS error without the " (os error N)" suffix, the way coreutils prints it.
+fn describe(err: &io::Error) -> String {
+    let message = err.to_string();
+    match message.find(" (os error ") {
+        Some(index) => message[..index].to_string(),
+        None => message
+    }
+}
+
+fn report(options: &RMOpts, path: &Path, err: &io::Error) {
+    // -f: files which are already gone aren't a problem
+    if options.force && err.kind() == io::ErrorKind::NotFound {
+        return;
+    }
+    FAILED.store(true, Ordering::Relaxed);
+    eprintln!("\x1b[0;91mrm: cannot remove '{}': {}\x1b[0m", path.to_string_lossy(), describe(err));
+}
+
+/// The kind of file as named in prompts, e.g. "rm: remove regular empty file 'a'?".
+fn file_kind(metadata: &Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        return "directory";
+    }
+    if file_type.is_symlink() {
+        return "symbolic link";
+    }
+    if file_type.is_file() {
+        return if metadata.len() == 0 { "regular empty file" } else { "regular file" };
+    }
+
+    #[cfg(unix)] {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return "fifo";
+        }
+        if file_type.is_socket() {
+            return "socket";
+        }
+        if file_type.is_block_device() {
+            return "block special file";
+        }
+        if file_type.is_char_device() {
+            return "character special file";
+        }
+    }
+
+    "file"
+}
+
+#[cfg(unix)]
+fn is_write_protected(path: &Path) -> bool {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    unsafe extern "C" {
+        fn access(path: *const u8, mode: i32) -> i32;
+    }
+
+    const W_OK: i32 = 2;
+
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: the path is a valid C string
+    unsafe { access(path.as_ptr().cast(), W_OK) != 0 }
+}
+
+#[cfg(not(unix))]
+fn is_write_protected(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly())
+}
+
+/// Asks whether to `action` ("remove" or "descend into") `path`, if the prompting mode calls for it.
+fn confirm(options: &RMOpts, path: &Path, metadata: &Metadata, action: &str) -> bool {
+    // nothing is going to happen, so there's nothing to ask about
+    if options.dry_run {
+        return true;
+    }
+
+    // the permissions of a symlink are meaningless
+    let write_protected = !metadata.is_symlink() && is_write_protected(path);
+    let ask = match options.prompt {
+        RMPrompting::Never => false,
+        RMPrompting::Sometimes | RMPrompting::Once => write_protected && stdin().is_terminal(),
+        RMPrompting::Always => true
+    };
+    if !ask {
+        return true;
+    }
+
+    prompt(format!("rm: {} {}{} '{}'? ",
+        action,
+        if write_protected { "write-protected " } else { "" },
+        file_kind(metadata),
+        path.to_string_lossy()).as_str())
+}
+
+/// Whether `path` is protected from removal (see `common::protect`). Reports it if so.
+fn protected(options: &RMOpts, path: &Path) -> bool {
+    let Some(reason) = options.protection.as_ref().and_then(|protection| protection.reason(path)) else {
+        return false;
+    };
+    eprintln!("\x1b[0;91mrm: refusing to remove '{}': protected by {} (use --force-protected to override)\x1b[0m",
+        path.to_string_lossy(), reason);
+    FAILED.store(true, Ordering::Relaxed);
+    true
+}
+
+/// `--exclude`, `--older-than` and `--larger-than`: whether `path` has to be left alone.
+/// Directories are only ever kept by `--exclude`, otherwise they go once they're empty.
+fn keep(options: &RMOpts, path: &Path, metadata: &Metadata) -> bool {
+    let excluded = options.exclude.iter().any(|pattern| glob::matches_path(pattern, path));
+    let filtered = !metadata.is_dir() && (
+        options.older_than.is_some_and(|cutoff| metadata.modified().is_ok_and(|modified| modified >= cutoff))
+            || options.larger_than.is_some_and(|size| metadata.len() <= size));
+    if !excluded && !filtered {
+        return false;
+    }
+
+    if options.verbose {
+        println!("{} '{}'", if options.dry_run { "would keep" } else { "kept" }, path.to_string_lossy());
+    }
+    true
+}
+
+/// Unlinks anything that isn't a directory, returns whether it was removed.
+fn remove_file(options: &RMOpts, path: &Path, metadata: &Metadata) -> bool {
+    if keep(options, path, metadata) || !confirm(options, path, metadata, "remove") {
+        return false;
+    }
+
+    if options.dry_run {
+        plan(path, metadata, if options.shred.is_some() && metadata.is_file() { "shred" } else { "remove" });
+        return true;
+    }
+
+    // symlinks and special files have no contents of their own to shred
+    if let Some(passes) = options.shred
+        && metadata.is_file() {
+        let shredded = shred::overwrite(path, passes).and_then(|_| shred::obscure(path));
+        return match shredded.and_then(fs::remove_file) {
+            Ok(_) => {
+                if options.verbose {
+                    println!("shredded '{}'", path.to_string_lossy());
+                }
+                true
+            },
+            Err(err) => {
+                report(options, path, &err);
+                false
+            }
+        };
+    }
+
+    match fs::remove_file(path) {
+        Ok(_) => {
+            if options.verbose {
+                println!("removed '{}'", path.to_string_lossy());
+            }
+            true
+        },
+        Err(err) => {
+            report(options, path, &err);
+            false
+        }
+    }
+}
+
+/// Removes a directory which should now be empty, returns whether it was removed.
+fn remove_directory(options: &RMOpts, path: &Path, metadata: &Metadata) -> bool {
+    if !confirm(options, path, metadata, "remove") {
+        return false;
+    }
+
+    if options.dry_run {
+        plan(path, metadata, "remove directory");
+        return true;
+    }
+
+    match fs::remove_dir(path) {
+        Ok(_) => {
+            if options.verbose {
+                println!("removed directory '{}'", path.to_string_lossy());
+            }
+            true
+        },
+        Err(err) => {
+            report(options, path, &err);
+            false
+        }
+    }
+}
+
+/// `--one-file-system`: whether to skip a directory because it's a mount point. Reports it if so.
+fn on_other_device(options: &RMOpts, path: &Path, metadata: &Metadata, root_device: u64) -> bool {
+    if !options.one_file_system || device(metadata) == root_device {
+        return false;
+    }
+    eprintln!("\x1b[0;91mrm: skipping '{}', since it's on a different device\x1b[0m", path.to_string_lossy());
+    FAILED.store(true, Ordering::Relaxed);
+    true
+}
+
+/// Whether every entry is asked about, which rules out removing in parallel. Otherwise only the odd
+/// write-protected entry is, and the workers take turns at asking (see `PROMPTING`).
+fn prompts_for_everything(options: &RMOpts) -> bool {
+    options.prompt == RMPrompting::Always
+}
+
+/// Removes a directory and everything below it, depth first. Failures are reported and the rest of the
+/// tree is still removed; returns whether everything (including `path` itself) was removed.
+fn remove_tree(options: &RMOpts, path: &Path, metadata: &Metadata, root_device: u64) -> bool {
+    let mut entries = match fs::read_dir(path) {
+        Ok(entries) => entries.peekable(),
+        Err(err) => {
+            report(options, path, &err);
+            return false;
+        }
     };
+
+    if entries.peek().is_some() && !confirm(options, path, metadata, "descend into") {
+        return false;
+    }
+
+    let mut complete = true;
+    for entry in entries {
+        let entry_path = match entry {
+            Ok(entry) => entry.path(),
+            Err(err) => {
+                report(options, path, &err);
+                complete = false;
+                continue;
+            }
+        };
+        // never follow symlinks, they're unlinked like any other file
+        let entry_metadata = match fs::symlink_metadata(&entry_path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                report(options, &entry_path, &err);
+                complete = false;
+                continue;
+            }
+        };
+
+        if protected(options, &entry_path) {
+            complete = false;
+            continue;
+        }
+
+        if entry_metadata.is_dir() {
+            if keep(options, &entry_path, &entry_metadata)
+                || on_other_device(options, &entry_path, &entry_metadata, root_device) {
+                complete = false;
+                continue;
+            }
+            complete &= remove_tree(options, &entry_path, &entry_metadata, root_device);
+        } else {
+            complete &= remove_file(options, &entry_path, &entry_metadata);
+        }
+    }
+
+    // whatever was left behind has already been reported
+    complete && remove_directory(options, path, metadata)
+}
+
+/// `-I`: asks once before removing more than three files or removing recursively.
+fn prompt_once(options: &RMOpts, paths: &[PathBuf]) -> bool {
+    if paths.len() <= 3 && !options.recursive {
+        return true;
+    }
+
+    let names: Vec<String> = paths.iter()
+        .map(|path| format!("'{}'", path.to_string_lossy()))
+        .collect();
+    prompt(format!("rm: remove {} argument{}{} ({})? ",
+        paths.len(),
+        if paths.len() == 1 { "" } else { "s" },
+        if options.recursive { " recursively" } else { "" },
+        names.join(", ")).as_str())
 }
 
-fn rm(options: &RMOpts, paths: &Vec<PathBuf>) {
+/// A trailing slash makes the OS resolve a symlink, so `link/` is taken to mean `link`; otherwise
+/// `rm -r link/` would empty the directory the link points to.
+fn unfollowed(path: &Path) -> PathBuf {
+    if !path.as_os_str().as_encoded_bytes().ends_with(b"/") {
+        return path.to_path_buf();
+    }
+    let link: PathBuf = path.components().collect();
+    match fs::symlink_metadata(&link) {
+        Ok(metadata) if metadata.is_symlink() => link,
+        _ => path.to_path_buf()
+    }
+}
+
+fn rm(options: &RMOpts, paths: &[PathBuf]) {
+    if options.prompt == RMPrompting::Once && !options.dry_run && !prompt_once(options, paths) {
+        return;
+    }
+
     for path in paths {
-        if !path.exists() {
-            println!("\x1b[0;91mrm: cannot remove '{}': No such file or directory\x1b[0m",
-                path.to_string_lossy());
+        let path = &unfollowed(path);
+        // never follows symlinks: a symlink is removed itself, whatever (if anything) it points to
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                report(options, path, &err);
+                continue;
+            }
+        };
+
+        if protected(options, path) {
             continue;
         }
 
-        if path.is_dir() {
-            if options.prompt == RMPrompting::Always &&
-                !prompt(
-                    format!("rm: remove directory '{}'? ",
-                        path.to_string_lossy()).as_str()) {
+        if metadata.is_dir() {
+            if keep(options, path, &metadata) {
                 continue;
             }
-            if options.recursive {
-                match fs::remove_dir_all(path) {
-                    Ok(_) => continue,
-                    Err(_) => println!("\x1b[0;91mrm: cannot remove '{}': an unexpected error occurred\x1b[0m",
-                        path.to_string_lossy())
-                }
-            } else if options.directories == true {
-                match path.is_empty() {
-                    Ok(_) => {},
-                    Err(_) => continue
-                }
-                match fs::remove_dir(path) {
-                    Ok(_) => {
-                        if options.verbose {
-                            println!("removed empty directory '{}'", path.to_string_lossy());
-                        }
+            if !options.recursive && !options.directories {
+                FAILED.store(true, Ordering::Relaxed);
+                eprintln!("\x1b[0;91mrm: cannot remove '{}': Is a directory\x1b[0m", path.to_string_lossy());
+                continue;
+            }
+            if !options.recursive {
+                // -d: checked up front so nothing is prompted for that can't be done anyway
+                match path.is_empty_dir() {
+                    Ok(true) => {},
+                    Ok(false) => {
+                        FAILED.store(true, Ordering::Relaxed);
+                        eprintln!("\x1b[0;91mrm: cannot remove '{}': Directory not empty\x1b[0m", path.to_string_lossy());
+                        continue;
                     },
-                    Err(_) => continue
+                    Err(err) => {
+                        report(options, path, &err);
+                        continue;
+                    }
                 }
+                if !options.trash {
+                    remove_directory(options, path, &metadata);
+                } else if confirm(options, path, &metadata, "remove") {
+                    move_to_trash(options, path);
+                }
+                continue;
             }
-        } else if path.is_file() {
-            if options.prompt == RMPrompting::Always &&
-                !prompt(
-                    format!("rm: remove regular file '{}'? ",
-                        path.to_string_lossy()).as_str()) {
+            if !check_preserve_root(options, path) {
                 continue;
             }
-            match fs::remove_file(path) {
-                Ok(_) => {
-                    if options.verbose {
-                        println!("removed '{}'", path.to_string_lossy());
-                    }
-                },
-                Err(_) => {}
+            if !options.trash {
+                if options.jobs > 1 && !options.dry_run && !prompts_for_everything(options) {
+                    parallel::remove_tree(options, path, &metadata, device(&metadata), options.jobs);
+                } else {
+                    remove_tree(options, path, &metadata, device(&metadata));
+                }
+            } else if confirm(options, path, &metadata, "remove") {
+                move_to_trash(options, path);
             }
         } else {
-            println!("\x1b[0;91mrm: cannot remove '{}': No such file or directory\x1b[0m",
-                path.to_string_lossy());
+            // regular files, symlinks, fifos, sockets and devices are all just unlinked
+            if !options.trash {
+                remove_file(options, path, &metadata);
+                continue;
+            }
+            if confirm(options, path, &metadata, "remove") {
+                move_to_trash(options, path);
+            }
         }
     }
 }
@@ -135,18 +683,28 @@ fn rm(options: &RMOpts, paths: &Vec<PathBuf>) {
 fn main() {
     let mut options: RMOpts = RMOpts {
         force: false,
-        prompt: RMPrompting::Never,
+        prompt: RMPrompting::Sometimes,
         recursive: false,
+        preserve_root: RMPreserveRoot::Root,
+        one_file_system: false,
         directories: false,
         verbose: false,
-        version: false
+        trash: false,
+        shred: None,
+        dry_run: false,
+        jobs: thread::available_parallelism().map_or(1, |n| n.get()),
+        exclude: Vec::new(),
+        older_than: None,
+        larger_than: None,
+        protection: None
     };
 
     let mut paths: Vec<PathBuf> = Vec::new();
+    let mut force_protected = false;
 
     let args: Vec<String> = env::args().collect();
     let mut expanded_args: Vec<String> = Vec::new();
-    for arg in args.iter().skip(1) {
+    for arg in config::default_args("rm").iter().chain(args.iter().skip(1)) {
         if arg.len() > 2 && arg.starts_with('-') && !arg.starts_with("--") {
             for ch in arg.chars().skip(1) {
                 expanded_args.push(format!("-{}", ch));
@@ -155,61 +713,135 @@ fn main() {
             expanded_args.push(arg.to_string());
         }
     }
-    for arg in expanded_args {
+    let mut expanded_args = expanded_args.into_iter();
+    while let Some(arg) = expanded_args.next() {
         match arg.as_str() {
-            "-f" | "--force" => options.force = true,
-            "-i" => options.prompt = RMPrompting::Always,
-            "-I" => options.prompt = RMPrompting::Once,
+            // whichever of -f, -i and -I comes last wins
+            "-f" | "--force" => {
+                options.force = true;
+                options.prompt = RMPrompting::Never;
+            },
+            "-i" | "--interactive" | "--interactive=always" => {
+                options.force = false;
+                options.prompt = RMPrompting::Always;
+            },
+            "-I" | "--interactive=once" => {
+                options.force = false;
+                options.prompt = RMPrompting::Once;
+            },
             "--interactive=never" => options.prompt = RMPrompting::Never,
-            "--interactive=once" => options.prompt = RMPrompting::Once,
-            "--interactive" | "--interactive=always" => options.prompt = RMPrompting::Always,
             "-r" | "-R" | "--recursive" => options.recursive = true,
+            "--preserve-root" => options.preserve_root = RMPreserveRoot::Root,
+            "--preserve-root=all" => options.preserve_root = RMPreserveRoot::All,
+            "--no-preserve-root" => options.preserve_root = RMPreserveRoot::No,
+            "--one-file-system" => options.one_file_system = true,
             "-d" | "--dir" => options.directories = true,
             "-v" | "--verbose" => options.verbose = true,
+            "--trash" => options.trash = true,
+            "--no-trash" => options.trash = false,
+            "--dry-run" => options.dry_run = true,
+            "--force-protected" => force_protected = true,
+            "--jobs" => {
+                let jobs = expanded_args.next().unwrap_or_default();
+                match jobs.parse() {
+                    Ok(jobs) if jobs > 0 => options.jobs = jobs,
+                    _ => {
+                        eprintln!("\x1b[0;91mError: Invalid number of jobs '{}'.\x1b[0m", jobs);
+                        process::exit(1);
+                    }
+                }
+            },
+            arg if arg.starts_with("--exclude=") =>
+                options.exclude.push(arg["--exclude=".len()..].to_string()),
+            arg if arg.starts_with("--older-than=") => {
+                let age = &arg["--older-than=".len()..];
+                // too great an age to subtract from now mustn't quietly turn the filter off
+                match time::parse_duration(age).and_then(|age| SystemTime::now().checked_sub(age)) {
+                    Some(cutoff) => options.older_than = Some(cutoff),
+                    None => {
+                        eprintln!("\x1b[0;91mError: Invalid duration '{}'.\x1b[0m", age);
+                        process::exit(1);
+                    }
+                }
+            },
+            arg if arg.starts_with("--larger-than=") => {
+                let larger_than = &arg["--larger-than=".len()..];
+                match size::parse(larger_than) {
+                    Some(larger_than) => options.larger_than = Some(larger_than),
+                    None => {
+                        eprintln!("\x1b[0;91mError: Invalid size '{}'.\x1b[0m", larger_than);
+                        process::exit(1);
+                    }
+                }
+            },
+            arg if arg.starts_with("--jobs=") => {
+                let jobs = &arg["--jobs=".len()..];
+                match jobs.parse() {
+                    Ok(jobs) if jobs > 0 => options.jobs = jobs,
+                    _ => {
+                        eprintln!("\x1b[0;91mError: Invalid number of jobs '{}'.\x1b[0m", jobs);
+                        process::exit(1);
+                    }
+                }
+            },
+            "--shred" => options.shred = Some(3),
+            arg if arg.starts_with("--shred=") => {
+                let passes = &arg["--shred=".len()..];
+                match passes.parse() {
+                    Ok(passes) => options.shred = Some(passes),
+                    Err(_) => {
+                        eprintln!("\x1b[0;91mError: Invalid number of passes '{}'.\x1b[0m", passes);
+                        process::exit(1);
+                    }
+                }
+            },
             "--help" => {
-                println!("Usage: rm [OPTION]... [FILE]...
-Remove (unlink) the FILE(s).
-
-  -f, --force           ignore nonexistent files and arguments, never prompt
-  -i                    prompt before every removal
-  -I                    prompt once before removing more than three files, or
-                          when removing recursively; less intrusive than -i,
-                          while still giving protection against most mistakes
-      --interactive[=WHEN]  prompt according to WHEN: never, once (-I), or
-                          always (-i); without WHEN, prompt always
-  -r, -R, --recursive   remove directories and their contents recursively
-  -d, --dir             remove empty directories
-  -v, --verbose         explain what is being done
-      --help        display this help and exit
-
-By default, rm does not remove directories.  Use the --recursive (-r or -R)
-option to remove each listed directory, too, along with all of its contents.
-
-To remove a file whose name starts with a '-', for example '-foo',
-use this command:
-  rm ./-foo
-
-Note that if you use rm to remove a file, it might be possible to recover
-some of its contents, given sufficient expertise and/or time.  For greater
-assurance that the contents are truly unrecoverable, consider using shred(1).
-
-GitHub Repo: <https://github.com/Matt-DESTROYER/cli-tools>");
+                print!("{}", RM.help());
+                return;
+            },
+            "--version" => {
+                print!("{}", RM.version());
                 return;
             },
-            "--version" => options.version = true,
             arg if !arg.starts_with('-') && !arg.starts_with("--") =>
                 paths.push(PathBuf::from(arg.to_string())),
             _ => {
-                println!("\x1b[0;91mError: Unknown argument '{}'.\x1b[0m", arg);
-                return;
+                eprintln!("\x1b[0;91mError: Unknown argument '{}'.\x1b[0m", arg);
+                process::exit(1);
             }
         }
     }
 
-    if paths.len() == 0 {
-        println!("\x1b[0;91mError: No paths supplied.\x1b[0m");
-        return;
+    // the whole point of --shred is that nothing survives, not even in the trash
+    if options.shred.is_some() {
+        options.trash = false;
+    }
+
+    if !force_protected {
+        options.protection = Some(Protection::load());
+    }
+
+    let filtering = !options.exclude.is_empty() || options.older_than.is_some() || options.larger_than.is_some();
+    if filtering && options.trash {
+        eprintln!("\x1b[0;91mError: --exclude, --older-than and --larger-than can't be used with --trash.\x1b[0m");
+        process::exit(1);
+    }
+
+    if paths.is_empty() {
+        if options.force {
+            return;
+        }
+        eprintln!("\x1b[0;91mError: No paths supplied.\x1b[0m");
+        process::exit(1);
     }
     
     rm(&options, &paths);
+
+    if options.dry_run {
+        print_plan();
+    }
+
+    if FAILED.load(Ordering::Relaxed) {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,152 @@
+use common::pool::Pool;
+
+use crate::{
+    RMOpts,
+    confirm,
+    keep,
+    on_other_device,
+    protected,
+    remove_directory,
+    remove_file,
+    report
+};
+
+use std::{
+    fs::{self, DirEntry, Metadata},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering}
+    }
+};
+
+/// A directory which is being emptied.
+struct Directory {
+    path: PathBuf,
+    metadata: Metadata,
+    parent: Option<Arc<Directory>>,
+    /// the scan of this directory plus every subdirectory which hasn't been removed yet
+    remaining: AtomicUsize,
+    /// cleared when something inside couldn't be removed, so this directory has to stay
+    complete: AtomicBool
+}
+
+impl Directory {
+    fn new(path: PathBuf, metadata: Metadata, parent: Option<Arc<Directory>>) -> Arc<Self> {
+        Arc::new(Directory {
+            path,
+            metadata,
+            parent,
+            remaining: AtomicUsize::new(1),
+            complete: AtomicBool::new(true)
+        })
+    }
+}
+
+/// Removes a tree with a pool of threads. Workers take directories from the pool, unlink the files in
+/// them and queue their subdirectories; the last one to finish inside a directory removes it, and so on
+/// up the tree.
+struct Remover<'a> {
+    options: &'a RMOpts,
+    root_device: u64,
+    pool: Pool<Arc<Directory>>,
+    removed: AtomicBool
+}
+
+impl Remover<'_> {
+    /// Called once nothing is left to do inside `directory`.
+    fn finish(&self, directory: &Arc<Directory>) {
+        let removed = directory.complete.load(Ordering::Acquire)
+            && remove_directory(self.options, &directory.path, &directory.metadata);
+
+        match &directory.parent {
+            Some(parent) => {
+                if !removed {
+                    parent.complete.store(false, Ordering::Release);
+                }
+                if parent.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    self.finish(parent);
+                }
+            },
+            None => self.removed.store(removed, Ordering::Release)
+        }
+    }
+
+    /// Unlinks or queues one entry of `directory`.
+    fn remove_entry(&self, directory: &Arc<Directory>, entry: io::Result<DirEntry>) {
+        let entry_path = match entry {
+            Ok(entry) => entry.path(),
+            Err(err) => {
+                report(self.options, &directory.path, &err);
+                directory.complete.store(false, Ordering::Release);
+                return;
+            }
+        };
+        let metadata = match fs::symlink_metadata(&entry_path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                report(self.options, &entry_path, &err);
+                directory.complete.store(false, Ordering::Release);
+                return;
+            }
+        };
+
+        if protected(self.options, &entry_path) {
+            directory.complete.store(false, Ordering::Release);
+            return;
+        }
+
+        if metadata.is_dir() {
+            if keep(self.options, &entry_path, &metadata)
+                || on_other_device(self.options, &entry_path, &metadata, self.root_device) {
+                directory.complete.store(false, Ordering::Release);
+                return;
+            }
+            // counted before it's queued, so this directory can't be finished early
+            directory.remaining.fetch_add(1, Ordering::AcqRel);
+            self.pool.push(Directory::new(entry_path, metadata, Some(directory.clone())));
+        } else if !remove_file(self.options, &entry_path, &metadata) {
+            directory.complete.store(false, Ordering::Release);
+        }
+    }
+
+    fn scan(&self, directory: &Arc<Directory>) {
+        match fs::read_dir(&directory.path) {
+            Ok(entries) => {
+                let mut entries = entries.peekable();
+                // a write-protected directory is only emptied if the user says so
+                if entries.peek().is_some()
+                    && !confirm(self.options, &directory.path, &directory.metadata, "descend into") {
+                    directory.complete.store(false, Ordering::Release);
+                } else {
+                    for entry in entries {
+                        self.remove_entry(directory, entry);
+                    }
+                }
+            },
+            Err(err) => {
+                report(self.options, &directory.path, &err);
+                directory.complete.store(false, Ordering::Release);
+            }
+        }
+
+        if directory.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.finish(directory);
+        }
+    }
+}
+
+/// The parallel counterpart of `remove_tree()`, for when not every entry needs to be prompted for.
+pub fn remove_tree(options: &RMOpts, path: &Path, metadata: &Metadata, root_device: u64, jobs: usize) -> bool {
+    let remover = Remover {
+        options,
+        root_device,
+        pool: Pool::new(),
+        removed: AtomicBool::new(false)
+    };
+    remover.pool.push(Directory::new(path.to_path_buf(), metadata.clone(), None));
+    remover.pool.run(jobs, |directory| remover.scan(&directory));
+
+    remover.removed.load(Ordering::Acquire)
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::hash_map::RandomState,
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{self, Seek, SeekFrom, Write},
+    path::{Path, PathBuf}
+};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// splitmix64, seeded from the randomness std uses for hash maps. Not cryptographic, but the point is
+/// only that the old contents are gone, not that the new ones can't be predicted.
+struct Random(u64);
+
+impl Random {
+    fn new() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(std::process::id() as u64);
+        Random(hasher.finish())
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill(&mut self, buffer: &mut [u8]) {
+        for chunk in buffer.chunks_mut(8) {
+            let bytes = self.next().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+fn open_for_writing(path: &Path) -> io::Result<File> {
+    match OpenOptions::new().write(true).open(path) {
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            // like `shred -f`, the file is about to be removed anyway
+            let mut permissions = fs::metadata(path)?.permissions();
+            #[cfg(unix)] {
+                use std::os::unix::fs::PermissionsExt;
+                permissions.set_mode(permissions.mode() | 0o200);
+            }
+            #[cfg(not(unix))]
+            permissions.set_readonly(false);
+            fs::set_permissions(path, permissions)?;
+            OpenOptions::new().write(true).open(path)
+        },
+        result => result
+    }
+}
+
+/// Overwrites the contents of a regular file `passes` times with random data and then once with zeros,
+/// syncing to disk after every pass.
+pub fn overwrite(path: &Path, passes: usize) -> io::Result<()> {
+    let mut file = open_for_writing(path)?;
+    let size = file.metadata()?.len();
+    let mut random = Random::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+
+    for pass in 0..=passes {
+        let zeros = pass == passes;
+        file.seek(SeekFrom::Start(0))?;
+        let mut remaining = size;
+        while remaining > 0 {
+            let length = remaining.min(BUFFER_SIZE as u64) as usize;
+            if zeros {
+                buffer[..length].fill(0);
+            } else {
+                random.fill(&mut buffer[..length]);
+            }
+            file.write_all(&buffer[..length])?;
+            remaining -= length as u64;
+        }
+        file.sync_all()?;
+    }
+
+    Ok(())
+}
+
+/// Renames `path` to ever shorter names of zeros (like `shred -u`) so the original name doesn't linger in
+/// the directory, returning the final name.
+pub fn obscure(path: &Path) -> io::Result<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    };
+    let length = match path.file_name() {
+        Some(name) => name.len(),
+        None => return Ok(path.to_path_buf())
+    };
+
+    let mut current = path.to_path_buf();
+    for length in (1..=length).rev() {
+        let candidate = parent.join("0".repeat(length));
+        // never rename over somebody else's file
+        if fs::symlink_metadata(&candidate).is_ok() {
+            continue;
+        }
+        fs::rename(&current, &candidate)?;
+        current = candidate;
+
+        #[cfg(unix)]
+        if let Ok(directory) = File::open(parent) {
+            let _ = directory.sync_all();
+        }
+    }
+
+    Ok(current)
+}
@@ -0,0 +1,362 @@
+use common::{
+    backup::{backup, BackupControl},
+    help::{Opt, Tool},
+    time::DateTime,
+    trash::{TrashDir, TrashedItem}
+};
+
+use std::{
+    env,
+    fs,
+    path::{self, PathBuf},
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime}
+};
+
+/// Set whenever something couldn't be done, for the exit status.
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+#[derive(PartialEq)]
+enum TrashCommand {
+    List,
+    Restore,
+    Purge,
+    Empty
+}
+
+struct TrashOpts {
+    backup: bool,
+    backup_control: BackupControl,
+    backup_suffix: String,
+    force: bool,
+    verbose: bool
+}
+
+/// Every trashed item, oldest first, so indices stay the same between runs.
+fn trashed_items() -> Vec<TrashedItem> {
+    let mut items: Vec<TrashedItem> = TrashDir::all()
+        .iter()
+        .flat_map(|trash_dir| trash_dir.items())
+        .collect();
+    items.sort_by(|a, b| {
+        a.info.deletion_date
+            .cmp(&b.info.deletion_date)
+            .then_with(|| a.info.path.cmp(&b.info.path))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    items
+}
+
+fn format_date(date: Option<DateTime>) -> String {
+    match date {
+        Some(date) => date.to_rfc3339().replace('T', " "),
+        None => "????-??-?? ??:??:??".to_string()
+    }
+}
+
+fn list(items: &[TrashedItem]) {
+    let width = items.len().to_string().len();
+    for (i, item) in items.iter().enumerate() {
+        println!("{:>width$}  {}  {}",
+            i + 1,
+            format_date(item.info.deletion_date),
+            item.info.path.to_string_lossy(),
+            width = width);
+    }
+}
+
+/// Finds the items an argument refers to: an index from `trash list`, an original path or a file name.
+fn resolve<'a>(items: &'a [TrashedItem], arg: &str) -> Result<&'a TrashedItem, String> {
+    if let Ok(index) = arg.parse::<usize>() {
+        return match index.checked_sub(1).and_then(|i| items.get(i)) {
+            Some(item) => Ok(item),
+            None => Err(format!("no item with index {} in the trash", index))
+        };
+    }
+
+    let absolute = path::absolute(arg).unwrap_or_else(|_| PathBuf::from(arg));
+    let matches: Vec<(usize, &TrashedItem)> = items.iter()
+        .enumerate()
+        .filter(|(_, item)| {
+            item.info.path == absolute
+                || item.name == arg
+                || item.info.path.file_name().is_some_and(|name| name == arg)
+        })
+        .collect();
+
+    match matches.as_slice() {
+        [] => Err(format!("'{}' is not in the trash", arg)),
+        [(_, item)] => Ok(item),
+        _ => {
+            let indices: Vec<String> = matches.iter()
+                .map(|(i, _)| (i + 1).to_string())
+                .collect();
+            Err(format!("'{}' is ambiguous, use one of the indices {}", arg, indices.join(", ")))
+        }
+    }
+}
+
+fn restore(item: &TrashedItem, options: &TrashOpts) {
+    let destination = &item.info.path;
+
+    if fs::symlink_metadata(destination).is_ok() {
+        if options.backup {
+            match backup(destination, options.backup_control, &options.backup_suffix) {
+                Some(backup_file) => {
+                    if options.verbose {
+                        println!("backup '{}' -> '{}'", destination.to_string_lossy(), backup_file);
+                    }
+                },
+                None if options.force => {},
+                None => {
+                    eprintln!("\x1b[0;91mtrash: cannot back up '{}'.\x1b[0m", destination.to_string_lossy());
+                    FAILED.store(true, Ordering::Relaxed);
+                    return;
+                }
+            }
+        } else if !options.force {
+            eprintln!("\x1b[0;91mtrash: cannot restore '{}': File exists (use --backup or --force)\x1b[0m",
+                destination.to_string_lossy());
+            FAILED.store(true, Ordering::Relaxed);
+            return;
+        }
+    }
+
+    if let Some(parent) = destination.parent()
+        && let Err(err) = fs::create_dir_all(parent) {
+        eprintln!("\x1b[0;91mtrash: cannot create directory '{}': {}\x1b[0m", parent.to_string_lossy(), err);
+        FAILED.store(true, Ordering::Relaxed);
+        return;
+    }
+
+    match fs::rename(item.file(), destination) {
+        Ok(_) => {
+            let _ = fs::remove_file(item.info_file());
+            if options.verbose {
+                println!("restored '{}'", destination.to_string_lossy());
+            }
+        },
+        Err(err) => {
+            eprintln!("\x1b[0;91mtrash: cannot restore '{}': {}\x1b[0m", destination.to_string_lossy(), err);
+            FAILED.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn purge(item: &TrashedItem, options: &TrashOpts) {
+    match item.purge() {
+        Ok(_) => {
+            if options.verbose {
+                println!("purged '{}'", item.info.path.to_string_lossy());
+            }
+        },
+        Err(err) => {
+            eprintln!("\x1b[0;91mtrash: cannot purge '{}': {}\x1b[0m", item.info.path.to_string_lossy(), err);
+            FAILED.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Permanently deletes everything in every trash directory, including files without a `.trashinfo`.
+fn empty(options: &TrashOpts) {
+    for trash_dir in TrashDir::all() {
+        for directory in [trash_dir.files(), trash_dir.info()] {
+            let entries = match fs::read_dir(&directory) {
+                Ok(entries) => entries,
+                Err(_) => continue
+            };
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                let removed = match entry.file_type() {
+                    Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&path),
+                    _ => fs::remove_file(&path)
+                };
+                match removed {
+                    Ok(_) => {
+                        if options.verbose {
+                            println!("removed '{}'", path.to_string_lossy());
+                        }
+                    },
+                    Err(err) => {
+                        eprintln!("\x1b[0;91mtrash: cannot remove '{}': {}\x1b[0m", path.to_string_lossy(), err);
+                        FAILED.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+const TRASH: Tool = Tool {
+    name: "trash",
+    version: env!("CARGO_PKG_VERSION"),
+    usage: "[COMMAND] [OPTION]... [ITEM]...",
+    description: "Work with the files `rm --trash` moved to the trash.
+
+Commands:
+  list                  list the trashed files with an index, the date they
+                          were trashed and where they came from (the default)
+  restore ITEM...       move each ITEM back to where it was trashed from
+  purge ITEM...         permanently delete each ITEM
+  empty [DAYS]          permanently delete everything in the trash, or only
+                          what was trashed more than DAYS days ago
+
+ITEM is an index shown by `trash list`, the original path of a trashed file,
+or just its file name.",
+    options: &[
+        Opt::new(&['b'], "backup", "when restoring, make a backup of a file in the way").value("[=CONTROL]"),
+        Opt::new(&['f'], "force", "when restoring, overwrite a file in the way"),
+        Opt::new(&['v'], "verbose", "explain what is being done")
+    ],
+    notes: "The backup suffix is '~'. The version control method may be selected via the
+--backup option or through the VERSION_CONTROL environment variable, as with mv.
+
+Both the home trash (~/.local/share/Trash) and the trash directories at the
+top of each mounted file system are used."
+};
+
+fn main() {
+    let mut options = TrashOpts {
+        backup: false,
+        backup_control: BackupControl::Existing,
+        backup_suffix: "~".to_string(),
+        force: false,
+        verbose: false
+    };
+
+    let mut command: Option<TrashCommand> = None;
+    let mut operands: Vec<String> = Vec::new();
+
+    if let Ok(version) = env::var("VERSION_CONTROL") {
+        match BackupControl::parse(&version) {
+            Some(control) => options.backup_control = control,
+            None => {
+                eprintln!("\x1b[0;91mError: Unknown version control '{}'.\x1b[0m", version);
+                process::exit(1);
+            }
+        }
+    }
+
+    let args: Vec<String> = env::args().collect();
+    let mut expanded_args: Vec<String> = Vec::new();
+    for arg in args.iter().skip(1) {
+        if arg.len() > 2 && arg.starts_with('-') && !arg.starts_with("--") {
+            for ch in arg.chars().skip(1) {
+                expanded_args.push(format!("-{}", ch));
+            }
+        } else {
+            expanded_args.push(arg.to_string());
+        }
+    }
+
+    for arg in expanded_args {
+        match arg.as_str() {
+            "-b" | "--backup" => options.backup = true,
+            "-f" | "--force" => options.force = true,
+            "-v" | "--verbose" => options.verbose = true,
+            "--help" => {
+                print!("{}", TRASH.help());
+                return;
+            },
+            "--version" => {
+                print!("{}", TRASH.version());
+                return;
+            },
+            arg if arg.starts_with("--backup=") => {
+                let version = &arg["--backup=".len()..];
+                options.backup = true;
+                match BackupControl::parse(version) {
+                    Some(control) => options.backup_control = control,
+                    None => {
+                        eprintln!("\x1b[0;91mError: Unknown version control '{}'.\x1b[0m", version);
+                        process::exit(1);
+                    }
+                }
+            },
+            arg if !arg.starts_with('-') && command.is_none() => {
+                command = Some(match arg {
+                    "list" | "ls" => TrashCommand::List,
+                    "restore" => TrashCommand::Restore,
+                    "purge" => TrashCommand::Purge,
+                    "empty" => TrashCommand::Empty,
+                    _ => {
+                        eprintln!("\x1b[0;91mError: Unknown command '{}'.\x1b[0m", arg);
+                        process::exit(1);
+                    }
+                });
+            },
+            arg if !arg.starts_with('-') => operands.push(arg.to_string()),
+            _ => {
+                eprintln!("\x1b[0;91mError: Unknown argument '{}'.\x1b[0m", arg);
+                process::exit(1);
+            }
+        }
+    }
+
+    let command = command.unwrap_or(TrashCommand::List);
+    let items = trashed_items();
+
+    match command {
+        TrashCommand::List => list(&items),
+        TrashCommand::Restore | TrashCommand::Purge => {
+            if operands.is_empty() {
+                eprintln!("\x1b[0;91mError: No items supplied.\x1b[0m");
+                process::exit(1);
+            }
+
+            // resolve everything up front, indices refer to the listing before anything changed
+            let mut selected: Vec<&TrashedItem> = Vec::new();
+            for operand in &operands {
+                match resolve(&items, operand) {
+                    Ok(item) => {
+                        if !selected.iter().any(|selected| std::ptr::eq(*selected, item)) {
+                            selected.push(item);
+                        }
+                    },
+                    Err(err) => {
+                        eprintln!("\x1b[0;91mtrash: {}\x1b[0m", err);
+                        FAILED.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+
+            for item in selected {
+                if command == TrashCommand::Restore {
+                    restore(item, &options);
+                } else {
+                    purge(item, &options);
+                }
+            }
+        },
+        TrashCommand::Empty => match operands.first() {
+            None => empty(&options),
+            Some(days) => {
+                let days: u64 = match days.parse() {
+                    Ok(days) => days,
+                    Err(_) => {
+                        eprintln!("\x1b[0;91mError: Invalid number of days '{}'.\x1b[0m", days);
+                        process::exit(1);
+                    }
+                };
+                // nothing can have been trashed that long ago
+                let Some(cutoff) = days.checked_mul(24 * 60 * 60)
+                    .and_then(|seconds| SystemTime::now().checked_sub(Duration::from_secs(seconds))) else {
+                    return;
+                };
+                for item in &items {
+                    // items without a (valid) deletion date can't be known to be old enough
+                    if item.info.deletion_date
+                        .and_then(DateTime::to_system_time)
+                        .is_some_and(|date| date < cutoff) {
+                        purge(item, &options);
+                    }
+                }
+            }
+        }
+    }
+
+    if FAILED.load(Ordering::Relaxed) {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,338 @@
+use common::{
+    copy,
+    help::{Opt, Tool},
+    journal::{self, Entry, Fingerprint, Kind, Operation},
+    time::DateTime
+};
+
+use std::{
+    collections::HashSet,
+    env,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    process
+};
+
+struct UndoOpts {
+    force: bool,
+    list: bool,
+    verbose: bool
+}
+
+fn describe(operation: &Operation) -> String {
+    format!("{} {}",
+        DateTime::local(operation.time).to_rfc3339().replace('T', " "),
+        operation.tool)
+}
+
+/// Prints the journal, newest operation first, numbered the way `undo N` counts.
+fn list(operations: &[Operation]) {
+    for (i, operation) in operations.iter().rev().enumerate() {
+        println!("{:>3}  {}", i + 1, describe(operation));
+        for entry in &operation.entries {
+            let kind = match entry.kind {
+                Kind::Move => "moved",
+                Kind::Backup => "backed up",
+                Kind::Trash => "trashed"
+            };
+            println!("       {} '{}' -> '{}'",
+                kind,
+                entry.from.to_string_lossy(),
+                entry.to.to_string_lossy());
+        }
+    }
+}
+
+/// Finds everything that would stop `operation` from being undone cleanly, i.e. files which have
+/// since been changed or removed, or new files in the way of the originals.
+fn conflicts(operation: &Operation, options: &UndoOpts) -> Vec<String> {
+    let mut conflicts: Vec<String> = Vec::new();
+    // paths vacated and filled by the entries undone before the current one
+    let mut freed: HashSet<&Path> = HashSet::new();
+    let mut filled: HashSet<&Path> = HashSet::new();
+
+    for entry in operation.entries.iter().rev() {
+        let present = !freed.contains(entry.to.as_path())
+            && (filled.contains(entry.to.as_path()) || fs::symlink_metadata(&entry.to).is_ok());
+        if !present {
+            conflicts.push(format!("'{}' no longer exists", entry.to.to_string_lossy()));
+        } else if !options.force && Fingerprint::of(&entry.to) != Some(entry.fingerprint) {
+            conflicts.push(format!("'{}' has changed since", entry.to.to_string_lossy()));
+        }
+
+        let occupied = filled.contains(entry.from.as_path())
+            || (!freed.contains(entry.from.as_path()) && fs::symlink_metadata(&entry.from).is_ok());
+        if occupied {
+            conflicts.push(format!("'{}' already exists", entry.from.to_string_lossy()));
+        }
+
+        freed.insert(&entry.to);
+        filled.remove(entry.to.as_path());
+        filled.insert(&entry.from);
+        freed.remove(entry.from.as_path());
+    }
+
+    conflicts
+}
+
+/// Moves a file back, copying it (and everything below it) when it's on another device by now.
+fn move_back(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            copy::copy(from, to)?;
+            copy::remove(from)
+        },
+        result => result
+    }
+}
+
+/// The `.trashinfo` file belonging to a file in a trash `files` directory.
+fn trash_info(file: &Path) -> Option<PathBuf> {
+    let name = file.file_name()?.to_string_lossy();
+    let trash_dir = file.parent()?.parent()?;
+    Some(trash_dir.join("info").join(format!("{}.trashinfo", name)))
+}
+
+fn undo_entry(entry: &Entry, options: &UndoOpts) -> io::Result<()> {
+    move_back(&entry.to, &entry.from)?;
+    if entry.kind == Kind::Trash
+        && let Some(info) = trash_info(&entry.to) {
+        let _ = fs::remove_file(info);
+    }
+    if options.verbose {
+        println!("restored '{}' -> '{}'",
+            entry.to.to_string_lossy(),
+            entry.from.to_string_lossy());
+    }
+    Ok(())
+}
+
+/// Reverses every entry of `operation`, newest first. Returns whether all of it was undone.
+fn undo(operation: &Operation, options: &UndoOpts) -> bool {
+    let conflicts = conflicts(operation, options);
+    if !conflicts.is_empty() {
+        println!("\x1b[0;91mundo: cannot undo {}:\x1b[0m", describe(operation));
+        for conflict in conflicts {
+            println!("\x1b[0;91m  {}\x1b[0m", conflict);
+        }
+        return false;
+    }
+
+    let mut undone = 0;
+    let mut complete = true;
+    for entry in operation.entries.iter().rev() {
+        match undo_entry(entry, options) {
+            Ok(_) => undone += 1,
+            Err(err) => {
+                println!("\x1b[0;91mundo: cannot restore '{}' -> '{}': {}\x1b[0m",
+                    entry.to.to_string_lossy(),
+                    entry.from.to_string_lossy(),
+                    err);
+                complete = false;
+                break;
+            }
+        }
+    }
+
+    // whatever was undone must not be undone again, even if the rest failed
+    if let Err(err) = journal::forget(&operation.id, undone) {
+        println!("\x1b[0;91mundo: cannot update the journal: {}\x1b[0m", err);
+        complete = false;
+    }
+    complete
+}
+
+const UNDO: Tool = Tool {
+    name: "undo",
+    version: env!("CARGO_PKG_VERSION"),
+    usage: "[OPTION]... [N]",
+    description: "Reverse the last N (default 1) operations of mv and rm --trash.
+
+Every file mv moves, every backup it makes, and every file rm moves to the
+trash is recorded in a journal.  undo moves those files back, newest first,
+and restores any files that were backed up in their place.
+
+An operation is only undone if nothing has happened to its files since: a
+moved file must still be where it was moved to, unchanged, and nothing new
+may be in the way of where it came from.  Otherwise nothing is touched and
+the conflicts are listed.",
+    options: &[
+        Opt::new(&['f'], "force", "undo operations even if their files have changed since (files in the way \
+            are never overwritten)"),
+        Opt::new(&['l'], "list", "list the journal, newest operation first"),
+        Opt::new(&['v'], "verbose", "explain what is being done")
+    ],
+    notes: "The journal is kept in $XDG_STATE_HOME/cli-tools/journal, which is
+~/.local/state/cli-tools/journal by default."
+};
+
+fn main() {
+    let mut options = UndoOpts {
+        force: false,
+        list: false,
+        verbose: false
+    };
+
+    let mut count: Option<usize> = None;
+
+    let args: Vec<String> = env::args().collect();
+    let mut expanded_args: Vec<String> = Vec::new();
+    for arg in args.iter().skip(1) {
+        if arg.len() > 2 && arg.starts_with('-') && !arg.starts_with("--") {
+            for ch in arg.chars().skip(1) {
+                expanded_args.push(format!("-{}", ch));
+            }
+        } else {
+            expanded_args.push(arg.to_string());
+        }
+    }
+
+    for arg in expanded_args {
+        match arg.as_str() {
+            "-f" | "--force" => options.force = true,
+            "-l" | "--list" => options.list = true,
+            "-v" | "--verbose" => options.verbose = true,
+            "--help" => {
+                print!("{}", UNDO.help());
+                return;
+            },
+            "--version" => {
+                print!("{}", UNDO.version());
+                return;
+            },
+            arg if !arg.starts_with('-') && count.is_none() => {
+                match arg.parse() {
+                    Ok(n) => count = Some(n),
+                    Err(_) => {
+                        println!("\x1b[0;91mError: Invalid number of operations '{}'.\x1b[0m", arg);
+                        process::exit(1);
+                    }
+                }
+            },
+            _ => {
+                println!("\x1b[0;91mError: Unknown argument '{}'.\x1b[0m", arg);
+                process::exit(1);
+            }
+        }
+    }
+
+    let operations = journal::read();
+
+    if options.list {
+        list(&operations);
+        return;
+    }
+
+    if operations.is_empty() {
+        println!("undo: nothing to undo");
+        return;
+    }
+
+    // stop at the first failure, undoing anything older could clash with what's left of it
+    for operation in operations.iter().rev().take(count.unwrap_or(1)) {
+        if !undo(operation, &options) {
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::UNIX_EPOCH;
+
+    /// A fresh directory of its own for each test.
+    fn scratch(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("undo-test-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    /// An entry for a move from `from` to `to` which has already happened.
+    fn moved(kind: Kind, from: &Path, to: &Path) -> Entry {
+        Entry {
+            kind,
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            fingerprint: Fingerprint::of(to).unwrap()
+        }
+    }
+
+    fn operation(entries: Vec<Entry>) -> Operation {
+        Operation {
+            id: "0-1".to_string(),
+            time: UNIX_EPOCH,
+            tool: "mv".to_string(),
+            entries
+        }
+    }
+
+    const OPTIONS: UndoOpts = UndoOpts {
+        force: false,
+        list: false,
+        verbose: false
+    };
+
+    #[test]
+    fn untouched_moves_can_be_undone() {
+        let directory = scratch("untouched");
+        let (a, b) = (directory.join("a"), directory.join("b"));
+        fs::write(&b, "a").unwrap();
+        assert!(conflicts(&operation(vec![moved(Kind::Move, &a, &b)]), &OPTIONS).is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn missing_changed_and_occupied_files_conflict() {
+        let directory = scratch("conflicts");
+        let (a, b, c, d) = (directory.join("a"), directory.join("b"), directory.join("c"), directory.join("d"));
+        fs::write(&b, "b").unwrap();
+        fs::write(&d, "d").unwrap();
+        let entries = vec![moved(Kind::Move, &a, &b), moved(Kind::Move, &c, &d)];
+
+        fs::remove_file(&b).unwrap();
+        fs::write(&d, "changed").unwrap();
+        fs::write(&c, "new").unwrap();
+        let conflicts = conflicts(&operation(entries), &OPTIONS);
+        assert_eq!(conflicts, [
+            format!("'{}' has changed since", d.to_string_lossy()),
+            format!("'{}' already exists", c.to_string_lossy()),
+            format!("'{}' no longer exists", b.to_string_lossy())
+        ]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn force_ignores_changes_but_not_files_in_the_way() {
+        let directory = scratch("force");
+        let (a, b) = (directory.join("a"), directory.join("b"));
+        fs::write(&b, "b").unwrap();
+        let entries = vec![moved(Kind::Move, &a, &b)];
+        fs::write(&b, "changed").unwrap();
+        fs::write(&a, "in the way").unwrap();
+
+        let options = UndoOpts { force: true, ..OPTIONS };
+        assert_eq!(conflicts(&operation(entries), &options),
+            [format!("'{}' already exists", a.to_string_lossy())]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn earlier_entries_see_what_later_ones_vacate_and_fill() {
+        let directory = scratch("chain");
+        let (a, b, backup) = (directory.join("a"), directory.join("b"), directory.join("b~"));
+        // `mv --backup a b`: b was moved to b~, then a to b
+        fs::write(&backup, "old b").unwrap();
+        let backed_up = moved(Kind::Backup, &b, &backup);
+        fs::write(&b, "a").unwrap();
+        let entries = vec![backed_up, moved(Kind::Move, &a, &b)];
+        assert!(conflicts(&operation(entries), &OPTIONS).is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
+}